@@ -0,0 +1,53 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{de::DeserializeOwned, Serialize};
+
+const APP_CONFIG_DIR: &str = "space_invaders_classic";
+
+/// Location of a config file inside the platform config directory
+/// (`$XDG_CONFIG_HOME` or `~/.config` on Linux).
+pub fn config_path(file_name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_CONFIG_DIR).join(file_name))
+}
+
+/// Reads a RON config file, returning `None` when it is missing or unreadable.
+pub fn load_ron<T: DeserializeOwned>(file_name: &str) -> Option<T> {
+    let path = config_path(file_name)?;
+    let contents = fs::read_to_string(&path).ok()?;
+
+    match ron::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            warn!("Ignoring malformed config {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// Writes a RON config file atomically, through a temporary file renamed
+/// over the old one, so a crash mid-write never leaves it truncated.
+pub fn save_ron<T: Serialize>(file_name: &str, value: &T) {
+    if let Err(err) = write_ron(file_name, value) {
+        error!("Failed to save {}", err);
+    }
+}
+
+/// Same as `save_ron`, for callers that report the error themselves
+pub fn write_ron<T: Serialize>(file_name: &str, value: &T) -> Result<(), String> {
+    let path = config_path(file_name)
+        .ok_or_else(|| format!("{}: no config directory available", file_name))?;
+
+    ron::ser::to_string_pretty(value, PrettyConfig::new())
+        .map_err(|err| err.to_string())
+        .and_then(|contents| {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+            }
+            let tmp_path = path.with_extension("tmp");
+            fs::write(&tmp_path, contents).map_err(|err| err.to_string())?;
+            fs::rename(&tmp_path, &path).map_err(|err| err.to_string())
+        })
+        .map_err(|err| format!("{}: {}", path.display(), err))
+}
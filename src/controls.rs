@@ -0,0 +1,395 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    prelude::*,
+    input::{
+        Axis, InputSystem,
+        gamepad::{Gamepad, GamepadAxis, GamepadAxisType, GamepadButton, GamepadButtonType, GamepadEvent, GamepadEventType},
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+const BINDINGS_FILE: &str = "controls.ron";
+const BINDINGS_VERSION: u32 = 2;
+const DEFAULT_DEADZONE: f32 = 0.25;
+pub const CONTROL_SCHEMES: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Fire,
+    /// Uses the ship's ability, named after the first one in older bindings
+    #[serde(alias = "Shield")]
+    Ability,
+    Pause,
+    Confirm,
+}
+impl Action {
+    pub const ALL: [Action; 8] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::Fire,
+        Action::Ability,
+        Action::Pause,
+        Action::Confirm,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::Fire => "Fire",
+            Action::Ability => "Ability",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AxisDirection {
+    Negative,
+    Positive,
+}
+impl AxisDirection {
+    fn sign(&self) -> f32 {
+        match self {
+            AxisDirection::Negative => -1.,
+            AxisDirection::Positive => 1.,
+        }
+    }
+}
+
+/// A single physical input that can trigger an `Action`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+    Axis(GamepadAxisType, AxisDirection),
+}
+impl InputBinding {
+    pub fn is_keyboard(&self) -> bool {
+        matches!(self, InputBinding::Key(_))
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            InputBinding::Key(key) => format!("{:?}", key),
+            InputBinding::Button(button) => format!("{:?}", button),
+            InputBinding::Axis(axis, AxisDirection::Negative) => format!("{:?}-", axis),
+            InputBinding::Axis(axis, AxisDirection::Positive) => format!("{:?}+", axis),
+        }
+    }
+}
+
+/// Actions mapped to the inputs of one control scheme
+pub type SchemeBindings = HashMap<Action, Vec<InputBinding>>;
+
+/// Persisted mapping from actions to keyboard and gamepad inputs,
+/// one scheme per local player
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlBindings {
+    pub version: u32,
+    pub deadzone: f32,
+    pub schemes: Vec<SchemeBindings>,
+}
+impl Default for ControlBindings {
+    fn default() -> Self {
+        Self {
+            version: BINDINGS_VERSION,
+            deadzone: DEFAULT_DEADZONE,
+            schemes: (0..CONTROL_SCHEMES).map(default_scheme).collect(),
+        }
+    }
+}
+impl ControlBindings {
+    /// Loads saved bindings, falling back to defaults for an unknown
+    /// version and for any scheme or action missing from the file.
+    pub fn load() -> Self {
+        let mut bindings = config::load_ron::<Self>(BINDINGS_FILE)
+            .filter(|saved| saved.version == BINDINGS_VERSION)
+            .unwrap_or_default();
+
+        bindings.schemes.truncate(CONTROL_SCHEMES);
+        for scheme in 0..CONTROL_SCHEMES {
+            let defaults = default_scheme(scheme);
+            if bindings.schemes.len() <= scheme {
+                bindings.schemes.push(defaults);
+                continue;
+            }
+            for action in Action::ALL {
+                bindings.schemes[scheme]
+                    .entry(action)
+                    .or_insert_with(|| defaults[&action].clone());
+            }
+        }
+        bindings.deadzone = bindings.deadzone.clamp(0., 0.9);
+        bindings
+    }
+
+    pub fn save(&self) {
+        config::save_ron(BINDINGS_FILE, self);
+    }
+
+    pub fn bindings(&self, scheme: usize, action: Action) -> &[InputBinding] {
+        self.schemes
+            .get(scheme)
+            .and_then(|actions| actions.get(&action))
+            .map(|bindings| bindings.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Another action of `scheme` already triggered by `binding`
+    pub fn conflict(&self, scheme: usize, action: Action, binding: InputBinding) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|other| *other != action && self.bindings(scheme, *other).contains(&binding))
+    }
+
+    /// Replaces the first binding of the same device kind, so rebinding a key
+    /// keeps the gamepad binding and vice versa.
+    pub fn rebind(&mut self, scheme: usize, action: Action, binding: InputBinding) {
+        let bindings = self.schemes[scheme].entry(action).or_default();
+        bindings.retain(|b| *b != binding);
+
+        match bindings.iter().position(|b| b.is_keyboard() == binding.is_keyboard()) {
+            Some(idx) => bindings[idx] = binding,
+            None => bindings.push(binding),
+        }
+    }
+
+    pub fn reset_scheme(&mut self, scheme: usize) {
+        self.schemes[scheme] = default_scheme(scheme);
+    }
+}
+
+/// Player 1 plays on the left of the keyboard, player 2 on the arrows.
+/// Up already fires for player 2, whose vertical movement is left to the
+/// gamepad unless rebound. Gamepads fire with the right trigger, leaving
+/// South to confirm in menus. Gamepads are handed out in the order they
+/// were connected.
+fn default_scheme(scheme: usize) -> SchemeBindings {
+    use InputBinding::*;
+
+    let (left, right, up, down, fire, ability, pause, confirm) = match scheme {
+        0 => (
+            vec![Key(KeyCode::A)],
+            vec![Key(KeyCode::D)],
+            vec![Key(KeyCode::W)],
+            vec![Key(KeyCode::S)],
+            vec![Key(KeyCode::Space)],
+            vec![Key(KeyCode::LShift)],
+            vec![Key(KeyCode::Escape)],
+            vec![Key(KeyCode::Return)],
+        ),
+        _ => (
+            vec![Key(KeyCode::Left)],
+            vec![Key(KeyCode::Right)],
+            vec![],
+            vec![],
+            vec![Key(KeyCode::Up), Key(KeyCode::RControl)],
+            vec![Key(KeyCode::RShift)],
+            vec![Key(KeyCode::P)],
+            vec![Key(KeyCode::NumpadEnter)],
+        ),
+    };
+
+    Action::ALL
+        .iter()
+        .map(|action| {
+            let mut bindings = match action {
+                Action::MoveLeft => left.clone(),
+                Action::MoveRight => right.clone(),
+                Action::MoveUp => up.clone(),
+                Action::MoveDown => down.clone(),
+                Action::Fire => fire.clone(),
+                Action::Ability => ability.clone(),
+                Action::Pause => pause.clone(),
+                Action::Confirm => confirm.clone(),
+            };
+            bindings.extend(match action {
+                Action::MoveLeft => vec![
+                    Button(GamepadButtonType::DPadLeft),
+                    Axis(GamepadAxisType::LeftStickX, AxisDirection::Negative),
+                ],
+                Action::MoveRight => vec![
+                    Button(GamepadButtonType::DPadRight),
+                    Axis(GamepadAxisType::LeftStickX, AxisDirection::Positive),
+                ],
+                Action::MoveUp => vec![
+                    Button(GamepadButtonType::DPadUp),
+                    Axis(GamepadAxisType::LeftStickY, AxisDirection::Positive),
+                ],
+                Action::MoveDown => vec![
+                    Button(GamepadButtonType::DPadDown),
+                    Axis(GamepadAxisType::LeftStickY, AxisDirection::Negative),
+                ],
+                Action::Fire => vec![Button(GamepadButtonType::RightTrigger2)],
+                Action::Ability => vec![Button(GamepadButtonType::West)],
+                Action::Pause => vec![Button(GamepadButtonType::Start)],
+                Action::Confirm => vec![Button(GamepadButtonType::South)],
+            });
+            (*action, bindings)
+        })
+        .collect()
+}
+
+/// Gamepads currently plugged in, in connection order
+#[derive(Default)]
+pub struct ConnectedGamepads(pub Vec<Gamepad>);
+
+/// Per frame state of every action, merged from keyboard and gamepads.
+/// As a resource it holds the union of all schemes, which menus read.
+#[derive(Default)]
+pub struct ActionState {
+    values: HashMap<Action, f32>,
+    pressed: HashSet<Action>,
+    previous: HashSet<Action>,
+}
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action) && !self.previous.contains(&action)
+    }
+
+    /// Clears `just_pressed` for the rest of the frame, so a state change
+    /// triggered by an action isn't re-triggered by the next state's systems.
+    pub fn consume(&mut self, action: Action) {
+        self.previous.insert(action);
+    }
+
+    /// Strength of the action between 0 and 1; digital inputs are always 1 when held.
+    pub fn value(&self, action: Action) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.)
+    }
+
+    fn begin_frame(&mut self) {
+        self.previous = std::mem::take(&mut self.pressed);
+        self.values.clear();
+    }
+
+    fn set(&mut self, action: Action, value: f32) {
+        if value > 0. {
+            let current = self.values.entry(action).or_insert(0.);
+            *current = current.max(value);
+            self.pressed.insert(action);
+        }
+    }
+}
+
+/// Action state of each control scheme, indexed like `ControlBindings::schemes`
+#[derive(Default)]
+pub struct SchemeActions {
+    schemes: Vec<ActionState>,
+}
+impl SchemeActions {
+    /// Actions of a single scheme, or of every scheme combined for `None`
+    pub fn get<'a>(&'a self, scheme: Option<usize>, merged: &'a ActionState) -> &'a ActionState {
+        scheme
+            .and_then(|idx| self.schemes.get(idx))
+            .unwrap_or(merged)
+    }
+}
+
+pub struct ControlsPlugin;
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(ControlBindings::load())
+            .init_resource::<ConnectedGamepads>()
+            .init_resource::<ActionState>()
+            .init_resource::<SchemeActions>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                track_gamepads.label("track_gamepads").after(InputSystem)
+            )
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                update_action_state.after("track_gamepads")
+            )
+        ;
+    }
+}
+
+fn track_gamepads(
+    mut gamepads: ResMut<ConnectedGamepads>,
+    mut events: EventReader<GamepadEvent>,
+) {
+    for GamepadEvent(gamepad, event_type) in events.iter() {
+        match event_type {
+            GamepadEventType::Connected if !gamepads.0.contains(gamepad) => {
+                gamepads.0.push(*gamepad);
+            }
+            GamepadEventType::Disconnected => {
+                gamepads.0.retain(|pad| pad != gamepad);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_action_state(
+    mut merged: ResMut<ActionState>,
+    mut scheme_actions: ResMut<SchemeActions>,
+    bindings: Res<ControlBindings>,
+    gamepads: Res<ConnectedGamepads>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+) {
+    merged.begin_frame();
+    scheme_actions.schemes.resize_with(bindings.schemes.len(), ActionState::default);
+
+    for (scheme, actions) in scheme_actions.schemes.iter_mut().enumerate() {
+        actions.begin_frame();
+        let gamepad = gamepads.0.get(scheme).copied();
+
+        for action in Action::ALL {
+            let value = bindings
+                .bindings(scheme, action)
+                .iter()
+                .map(|binding| match (binding, gamepad) {
+                    (InputBinding::Key(key), _) => digital(keys.pressed(*key)),
+                    (InputBinding::Button(button), Some(pad)) => {
+                        digital(buttons.pressed(GamepadButton(pad, *button)))
+                    }
+                    (InputBinding::Axis(axis, direction), Some(pad)) => {
+                        let raw = axes.get(GamepadAxis(pad, *axis)).unwrap_or(0.);
+                        apply_deadzone(raw * direction.sign(), bindings.deadzone)
+                    }
+                    (_, None) => 0.,
+                })
+                .fold(0., f32::max);
+
+            actions.set(action, value);
+            merged.set(action, value);
+        }
+    }
+}
+
+fn digital(pressed: bool) -> f32 {
+    if pressed { 1. } else { 0. }
+}
+
+/// Rescales stick input so it ramps from 0 at the deadzone edge to 1 at full tilt
+fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    if value <= deadzone {
+        0.
+    } else {
+        ((value - deadzone) / (1. - deadzone)).min(1.)
+    }
+}
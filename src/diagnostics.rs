@@ -1,20 +1,25 @@
-use bevy::{prelude::*, app::PluginGroupBuilder, diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin}};
+mod overlay;
+
+use bevy::{prelude::*, app::PluginGroupBuilder, diagnostic::FrameTimeDiagnosticsPlugin};
+pub use self::overlay::*;
 
 pub struct DiagnosticsPluginGroup;
 impl PluginGroup for DiagnosticsPluginGroup {
     fn build(&mut self, group: &mut PluginGroupBuilder) {
         group
             // Adds frame time diagnostics
-            .add(FrameTimeDiagnosticsPlugin::default())
+            .add(FrameTimeDiagnosticsPlugin)
             // Adds a system that prints diagnostics to the console
-            // .add(LogDiagnosticsPlugin::default())
+            // .add(bevy::diagnostic::LogDiagnosticsPlugin::default())
             // Any plugin can register diagnostics
             // Uncomment this to add some render resource diagnostics:
             // .add_plugin(bevy::wgpu::diagnostic::WgpuResourceDiagnosticsPlugin::default())
             // Uncomment this to add an entity count diagnostics:
-            .add(bevy::diagnostic::EntityCountDiagnosticsPlugin::default())
+            .add(bevy::diagnostic::EntityCountDiagnosticsPlugin)
             // Uncomment this to add an asset count diagnostics:
             // .add_plugin(bevy::asset::diagnostic::AssetCountDiagnosticsPlugin::<Texture>::default())
+            // Shows the above on screen along with hitboxes, toggled with F3
+            .add(DebugOverlayPlugin)
             ;
     }
 }
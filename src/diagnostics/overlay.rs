@@ -0,0 +1,175 @@
+use bevy::{prelude::*, diagnostic::{Diagnostics, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin}, utils::HashSet};
+
+use crate::{FontInfos, entities::AlienState, shared::{Projectile, RenderedAssetInfo}};
+
+const DEBUG_OVERLAY_KEY: KeyCode = KeyCode::F3;
+const DEBUG_FONT_SIZE: f32 = 10.;
+const DEBUG_MARGIN: f32 = 8.;
+const HITBOX_COLOR: Color = Color::rgba(0., 1., 0.4, 0.8);
+const HITBOX_THICKNESS: f32 = 1.;
+/// Above every sprite of the game
+const HITBOX_Z: f32 = 50.;
+
+/// Hitboxes and a readout of frame rate, entity counts and fleet state,
+/// toggled with F3 in any state
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub visible: bool,
+}
+
+#[derive(Component)]
+struct DebugPanel;
+
+/// One edge of the outline drawn around the hitbox of `owner`, spawned
+/// once and moved along with it
+#[derive(Component)]
+struct HitboxEdge {
+    owner: Entity,
+    offset: Vec2,
+}
+
+pub struct DebugOverlayPlugin;
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<DebugOverlay>()
+            .add_system(toggle_debug_overlay.label("toggle_debug_overlay"))
+            .add_system(update_debug_panel.after("toggle_debug_overlay"))
+            .add_system(draw_hitboxes.after("toggle_debug_overlay"))
+        ;
+    }
+}
+
+fn toggle_debug_overlay(
+    keys: Res<Input<KeyCode>>,
+    mut overlay: ResMut<DebugOverlay>,
+) {
+    if keys.just_pressed(DEBUG_OVERLAY_KEY) {
+        overlay.visible = !overlay.visible;
+    }
+}
+
+fn update_debug_panel(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    diagnostics: Res<Diagnostics>,
+    // Neither exists before the assets are loaded or the first game started
+    font_infos: Option<Res<FontInfos>>,
+    alien_state: Option<Res<AlienState>>,
+    mut panel_q: Query<(Entity, &mut Text), With<DebugPanel>>,
+    projectile_q: Query<(), With<Projectile>>,
+) {
+    if !overlay.visible {
+        for (entity, _) in panel_q.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    let average = |id| diagnostics.get(id).and_then(|diagnostic| diagnostic.average());
+    let mut lines = vec![
+        format!("FPS: {:.0}", average(FrameTimeDiagnosticsPlugin::FPS).unwrap_or(0.)),
+        format!("Frame time: {:.2} ms", average(FrameTimeDiagnosticsPlugin::FRAME_TIME).unwrap_or(0.) * 1000.),
+        format!(
+            "Entities: {:.0}",
+            diagnostics
+                .get(EntityCountDiagnosticsPlugin::ENTITY_COUNT)
+                .and_then(|diagnostic| diagnostic.value())
+                .unwrap_or(0.),
+        ),
+        format!("Projectiles: {}", projectile_q.iter().count()),
+    ];
+    if let Some(alien_state) = alien_state {
+        lines.push(format!("{:#?}", *alien_state));
+    }
+    let value = lines.join("\n");
+
+    match panel_q.iter_mut().next() {
+        Some((_, mut text)) => text.sections[0].value = value,
+        None => {
+            let font = match font_infos {
+                Some(font_infos) => font_infos.main.clone(),
+                None => return,
+            };
+            commands
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            top: Val::Px(DEBUG_MARGIN * 4.),
+                            right: Val::Px(DEBUG_MARGIN),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        value,
+                        TextStyle {
+                            font,
+                            font_size: DEBUG_FONT_SIZE,
+                            color: Color::YELLOW,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(DebugPanel);
+        }
+    }
+}
+
+/// Outlines the box every collision check uses, centred on the entity
+fn draw_hitboxes(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    // Entities with an outline
+    mut outlined: Local<HashSet<Entity>>,
+    mut edge_q: Query<(Entity, &HitboxEdge, &mut Transform)>,
+    q: Query<(Entity, &RenderedAssetInfo, &GlobalTransform)>,
+) {
+    if !overlay.visible {
+        for (entity, _, _) in edge_q.iter() {
+            commands.entity(entity).despawn();
+        }
+        outlined.clear();
+        return;
+    }
+
+    // Outlines follow their entity, and go with it
+    for (entity, edge, mut tf) in edge_q.iter_mut() {
+        match q.get(edge.owner) {
+            Ok((_, _, gtf)) => tf.translation = (gtf.translation.truncate() + edge.offset).extend(HITBOX_Z),
+            Err(_) => {
+                commands.entity(entity).despawn();
+                outlined.remove(&edge.owner);
+            }
+        }
+    }
+
+    for (owner, rai, gtf) in q.iter() {
+        if !outlined.insert(owner) {
+            continue;
+        }
+        let center = gtf.translation.truncate();
+        let half = rai.size / 2.;
+        let edges = [
+            (Vec2::new(0., half.y), Vec2::new(rai.size.x, HITBOX_THICKNESS)),
+            (Vec2::new(0., -half.y), Vec2::new(rai.size.x, HITBOX_THICKNESS)),
+            (Vec2::new(-half.x, 0.), Vec2::new(HITBOX_THICKNESS, rai.size.y)),
+            (Vec2::new(half.x, 0.), Vec2::new(HITBOX_THICKNESS, rai.size.y)),
+        ];
+        for (offset, size) in edges {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: HITBOX_COLOR,
+                        custom_size: Some(size),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation((center + offset).extend(HITBOX_Z)),
+                    ..Default::default()
+                })
+                .insert(HitboxEdge { owner, offset });
+        }
+    }
+}
@@ -0,0 +1,154 @@
+mod director;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+pub use self::director::*;
+
+/// Bounds the director keeps the knobs within, whatever the preset
+const MIN_FIRE_INTERVAL: f32 = 0.3;
+const MAX_FIRE_INTERVAL: f32 = 2.;
+const MIN_SHOOTERS: i32 = 1;
+const MAX_SHOOTERS: i32 = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DifficultyPreset {
+    Easy,
+    Normal,
+    Hard,
+    /// As fast and aggressive as the cabinet gets
+    Arcade,
+}
+impl DifficultyPreset {
+    pub const ALL: [DifficultyPreset; 4] = [
+        DifficultyPreset::Easy,
+        DifficultyPreset::Normal,
+        DifficultyPreset::Hard,
+        DifficultyPreset::Arcade,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DifficultyPreset::Easy => "EASY",
+            DifficultyPreset::Normal => "NORMAL",
+            DifficultyPreset::Hard => "HARD",
+            DifficultyPreset::Arcade => "ARCADE",
+        }
+    }
+
+    /// Following preset, wrapping around to the first
+    pub fn next(&self) -> Self {
+        let idx = Self::ALL.iter().position(|preset| preset == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|preset| preset.label().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Every knob that makes the game easier or harder
+#[derive(Clone, Debug)]
+pub struct Difficulty {
+    pub preset: DifficultyPreset,
+    /// March speed of the fleet on the first wave
+    pub fleet_speed: f32,
+    /// March speed added with each wave
+    pub fleet_speed_per_wave: f32,
+    /// How far the fleet drops at each edge
+    pub descent_step: f32,
+    /// Aliens picked to shoot each time the fleet fires
+    pub shooters: u32,
+    /// Seconds between two fleet volleys on the first wave
+    pub fire_interval: f32,
+    /// Alien shots allowed on screen at once
+    pub max_alien_shots: u32,
+    /// Seconds between two aliens breaking formation to dive
+    pub dive_interval: f32,
+}
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self::from_preset(DifficultyPreset::Normal)
+    }
+}
+impl Difficulty {
+    pub fn from_preset(preset: DifficultyPreset) -> Self {
+        let normal = Self {
+            preset,
+            fleet_speed: 30.,
+            fleet_speed_per_wave: 5.,
+            descent_step: 10.,
+            shooters: 2,
+            fire_interval: 0.8,
+            max_alien_shots: 3,
+            dive_interval: 4.,
+        };
+
+        match preset {
+            DifficultyPreset::Easy => Self {
+                fleet_speed: 22.,
+                fleet_speed_per_wave: 3.,
+                shooters: 1,
+                fire_interval: 1.2,
+                max_alien_shots: 2,
+                dive_interval: 6.,
+                ..normal
+            },
+            DifficultyPreset::Normal => normal,
+            DifficultyPreset::Hard => Self {
+                fleet_speed: 40.,
+                fleet_speed_per_wave: 7.,
+                shooters: 3,
+                fire_interval: 0.6,
+                max_alien_shots: 4,
+                dive_interval: 3.,
+                ..normal
+            },
+            DifficultyPreset::Arcade => Self {
+                fleet_speed: 50.,
+                fleet_speed_per_wave: 10.,
+                descent_step: 16.,
+                shooters: 4,
+                fire_interval: 0.45,
+                max_alien_shots: 5,
+                dive_interval: 2.,
+                ..normal
+            },
+        }
+    }
+
+    /// Preset nudged `level` steps harder, easier when negative. Each step
+    /// is 10% on fire rate and march speed and half a shooter.
+    pub fn adjusted(&self, level: i32) -> Self {
+        let base = Self::from_preset(self.preset);
+        let factor = 1. + 0.1 * level as f32;
+        Self {
+            fleet_speed: base.fleet_speed * factor,
+            fleet_speed_per_wave: base.fleet_speed_per_wave * factor,
+            shooters: (base.shooters as i32 + level / 2).clamp(MIN_SHOOTERS, MAX_SHOOTERS) as u32,
+            fire_interval: (base.fire_interval / factor).clamp(MIN_FIRE_INTERVAL, MAX_FIRE_INTERVAL),
+            ..base
+        }
+    }
+
+    /// Preset given on the command line as `--difficulty <preset>`
+    pub fn from_args() -> Option<Self> {
+        let args = std::env::args().collect::<Vec<_>>();
+        let name = args
+            .iter()
+            .position(|arg| arg == "--difficulty")
+            .and_then(|idx| args.get(idx + 1))
+            .map(|name| name.as_str())
+            .or_else(|| args.iter().find_map(|arg| arg.strip_prefix("--difficulty=")))?;
+
+        match DifficultyPreset::parse(name) {
+            Some(preset) => Some(Self::from_preset(preset)),
+            None => {
+                warn!("Unknown difficulty {}, expected easy, normal, hard or arcade", name);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Game, GameState, entities::{Enemy, EnemyDestroyed, PlayerDied, PlayerFired}};
+
+use super::Difficulty;
+
+/// How far the director may stray from the preset, in steps
+const DIRECTOR_MAX_LEVEL: i32 = 3;
+/// Waves cleared faster than this with good aim get harder
+const FAST_CLEAR_SECS: f32 = 40.;
+/// Waves taking longer than this get easier
+const SLOW_CLEAR_SECS: f32 = 120.;
+const GOOD_ACCURACY: f32 = 0.5;
+const POOR_ACCURACY: f32 = 0.2;
+
+/// Adaptive difficulty, watches how each wave goes and nudges the
+/// preset harder or easier for the next one
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct DifficultyDirector {
+    pub enabled: bool,
+    /// Steps away from the preset, negative is easier
+    pub level: i32,
+    /// Wave the stats below belong to
+    wave: u32,
+    wave_time: f32,
+    shots: u32,
+    kills: u32,
+    deaths: u32,
+}
+impl DifficultyDirector {
+    /// Enabled by `--adaptive-difficulty` on the command line
+    pub fn from_args() -> Self {
+        Self {
+            enabled: std::env::args().any(|arg| arg == "--adaptive-difficulty"),
+            ..Default::default()
+        }
+    }
+
+    fn reset_wave(&mut self, wave: u32) {
+        self.wave = wave;
+        self.wave_time = 0.;
+        self.shots = 0;
+        self.kills = 0;
+        self.deaths = 0;
+    }
+
+    /// Kills per shot fired, 0 without shots
+    fn accuracy(&self) -> f32 {
+        if self.shots == 0 { 0. } else { self.kills as f32 / self.shots as f32 }
+    }
+
+    /// Step to take after a cleared wave
+    fn verdict(&self) -> i32 {
+        let accuracy = self.accuracy();
+        if self.deaths > 0 || accuracy < POOR_ACCURACY || self.wave_time > SLOW_CLEAR_SECS {
+            -1
+        } else if accuracy > GOOD_ACCURACY && self.wave_time < FAST_CLEAR_SECS {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+pub struct DifficultyPlugin;
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Difficulty::from_args().unwrap_or_default())
+            .insert_resource(DifficultyDirector::from_args())
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame)
+                    .with_system(reset_director)
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    // Counts the shots and deaths of the frame they happen
+                    // in, a restored projectile is never taken for a new one
+                    .with_system(track_performance.label("track_performance").after("player_lives"))
+                    .with_system(
+                        adjust_difficulty
+                            .after("track_performance")
+                            .before("wave_progress")
+                    )
+            )
+        ;
+    }
+}
+
+/// Every game starts from the chosen preset
+fn reset_director(
+    game: Res<Game>,
+    mut director: ResMut<DifficultyDirector>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    director.level = 0;
+    director.reset_wave(game.wave);
+    *difficulty = Difficulty::from_preset(difficulty.preset);
+}
+
+fn track_performance(
+    time: Res<Time>,
+    game: Res<Game>,
+    mut director: ResMut<DifficultyDirector>,
+    mut destroyed_events: EventReader<EnemyDestroyed>,
+    mut died_events: EventReader<PlayerDied>,
+    mut fired_events: EventReader<PlayerFired>,
+) {
+    // Also catches boards swapped in alternating play
+    if director.wave != game.wave {
+        director.reset_wave(game.wave);
+    }
+
+    director.wave_time += time.delta_seconds();
+    director.shots += fired_events.iter().count() as u32;
+    director.kills += destroyed_events.iter().count() as u32;
+    director.deaths += died_events.iter().count() as u32;
+}
+
+/// Runs before the next wave is sent in so it already gets the new settings
+fn adjust_difficulty(
+    mut director: ResMut<DifficultyDirector>,
+    mut difficulty: ResMut<Difficulty>,
+    enemy_q: Query<(), With<Enemy>>,
+) {
+    if !director.enabled || director.kills == 0 || enemy_q.iter().next().is_some() {
+        return;
+    }
+
+    let level = (director.level + director.verdict()).clamp(-DIRECTOR_MAX_LEVEL, DIRECTOR_MAX_LEVEL);
+    info!(
+        "Director: wave {} cleared in {:.1}s, accuracy {:.0}% ({}/{}), {} deaths, level {} -> {}",
+        director.wave,
+        director.wave_time,
+        director.accuracy() * 100.,
+        director.kills,
+        director.shots,
+        director.deaths,
+        director.level,
+        level,
+    );
+
+    if level != director.level {
+        director.level = level;
+        *difficulty = difficulty.adjusted(level);
+        info!(
+            "Director: {} shooters every {:.2}s, fleet speed {:.0}",
+            difficulty.shooters,
+            difficulty.fire_interval,
+            difficulty.fleet_speed,
+        );
+    }
+    // Only judged once, the stats reset when the next wave starts
+    director.kills = 0;
+}
@@ -1,20 +1,41 @@
+mod abilities;
+mod board;
+mod board_scene;
+mod boss;
+mod dive;
 mod enemy;
 mod player;
 mod obstacles;
+mod powerups;
+mod ships;
 
 use bevy::{app::PluginGroupBuilder, prelude::*};
+use crate::{GameState, shared::{Explosion, Projectile}};
+pub use self::abilities::*;
+pub use self::board::*;
+pub use self::board_scene::*;
+pub use self::boss::*;
+pub use self::dive::*;
 pub use self::player::*;
 pub use self::enemy::*;
 pub use self::obstacles::*;
+pub use self::powerups::*;
+pub use self::ships::*;
 
 pub struct EntitiesPluginGroup;
 impl PluginGroup for EntitiesPluginGroup {
     fn build(&mut self, group: &mut PluginGroupBuilder) {
         group
             .add(EntitiesPlugin)
+            .add(BoardPlugin)
+            .add(BoardScenePlugin)
             .add(ObstaclesPlugin)
             .add(PlayerPlugin)
+            .add(AbilitiesPlugin)
             .add(EnemyPlugin)
+            .add(BossPlugin)
+            .add(DivePlugin)
+            .add(PowerUpsPlugin)
         ;
     }
 }
@@ -22,5 +43,24 @@ impl PluginGroup for EntitiesPluginGroup {
 pub struct EntitiesPlugin;
 impl Plugin for EntitiesPlugin {
     fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_enter(GameState::MainMenu)
+                    .with_system(cleanup_board)
+            )
+        ;
+    }
+}
+
+/// Everything spawned for a game, cleared between games
+pub type InGameEntity = Or<(With<Player>, With<Enemy>, With<Obstacle>, With<Projectile>, With<PowerUp>, With<Explosion>, With<Boss>, With<Formation>, With<ShieldBubble>)>;
+
+/// Clears whatever is left of the previous game
+fn cleanup_board(
+    mut commands: Commands,
+    q: Query<Entity, InGameEntity>,
+) {
+    for entity in q.iter() {
+        commands.entity(entity).despawn();
     }
 }
\ No newline at end of file
@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{GameState, controls::{Action, ActionState, SchemeActions}, shared::RenderedAssetInfo};
+
+use super::{Player, PlayerState, Players};
+
+const SHIELD_BUBBLE_MARGIN: f32 = 12.;
+const SHIELD_BUBBLE_COLOR: Color = Color::rgba(0.4, 0.8, 1., 0.35);
+const BOOST_SPEED_FACTOR: f32 = 1.8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AbilityKind {
+    /// Absorbs enemy shots while up
+    Shield,
+    /// Raises the ship's acceleration and top speed while up
+    Boost,
+}
+impl AbilityKind {
+    /// Seconds the ability stays up
+    fn duration(&self) -> f32 {
+        match self {
+            AbilityKind::Shield => 3.,
+            AbilityKind::Boost => 2.,
+        }
+    }
+
+    /// Seconds before the ability can be used again, counted once it wears off
+    fn cooldown(&self) -> f32 {
+        match self {
+            AbilityKind::Shield => 12.,
+            AbilityKind::Boost => 6.,
+        }
+    }
+}
+
+/// Active ability of a player, gated by a cooldown
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Ability {
+    pub kind: AbilityKind,
+    /// Time left while the ability is up
+    #[serde(with = "crate::savegame::saved_timer::option")]
+    active: Option<Timer>,
+    #[serde(with = "crate::savegame::saved_timer")]
+    cooldown: Timer,
+}
+impl Ability {
+    pub fn new(kind: AbilityKind) -> Self {
+        let mut cooldown = Timer::from_seconds(kind.cooldown(), false);
+        // Ready from the start
+        cooldown.tick(cooldown.duration());
+        Self {
+            kind,
+            active: None,
+            cooldown,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        !self.is_active() && self.cooldown.finished()
+    }
+
+    /// Whether enemy shots hitting this player are absorbed
+    pub fn absorbs_shots(&self) -> bool {
+        self.kind == AbilityKind::Shield && self.is_active()
+    }
+
+    /// Factor applied to the ship's acceleration and top speed
+    pub fn speed_factor(&self) -> f32 {
+        if self.kind == AbilityKind::Boost && self.is_active() { BOOST_SPEED_FACTOR } else { 1. }
+    }
+
+    /// How far along the cooldown is, 1 once the ability is ready
+    pub fn charge(&self) -> f32 {
+        if self.is_active() { 0. } else { self.cooldown.percent() }
+    }
+
+    fn activate(&mut self) {
+        self.active = Some(Timer::from_seconds(self.kind.duration(), false));
+    }
+}
+
+/// Bubble drawn around a shielded ship
+#[derive(Component)]
+pub struct ShieldBubble {
+    owner: Entity,
+}
+
+pub struct AbilitiesPlugin;
+impl Plugin for AbilitiesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(use_abilities.label("use_abilities").before("collisions"))
+                    .with_system(show_shield_bubbles.after("use_abilities"))
+            )
+        ;
+    }
+}
+
+fn use_abilities(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    scheme_actions: Res<SchemeActions>,
+    players: Res<Players>,
+    mut q: Query<(&PlayerState, &mut Ability), With<Player>>,
+) {
+    for (player, mut ability) in q.iter_mut() {
+        let expired = match ability.active.as_mut() {
+            Some(active) => active.tick(time.delta()).finished(),
+            None => {
+                ability.cooldown.tick(time.delta());
+                false
+            }
+        };
+        if expired {
+            ability.active = None;
+            ability.cooldown.reset();
+        }
+
+        let player_actions = scheme_actions.get(players.slots[player.index].controls, &actions);
+        if ability.is_ready() && player_actions.just_pressed(Action::Ability) {
+            ability.activate();
+        }
+    }
+}
+
+/// Keeps a bubble on every shielded ship and removes it once the shield
+/// drops or the ship is gone
+fn show_shield_bubbles(
+    mut commands: Commands,
+    player_q: Query<(Entity, &Ability, &Transform, &RenderedAssetInfo), With<Player>>,
+    mut bubble_q: Query<(Entity, &ShieldBubble, &mut Transform), Without<Player>>,
+) {
+    for (bubble_en, bubble, mut tf) in bubble_q.iter_mut() {
+        match player_q.get(bubble.owner) {
+            Ok((_, ability, player_tf, _)) if ability.absorbs_shots() => {
+                tf.translation.x = player_tf.translation.x;
+                tf.translation.y = player_tf.translation.y;
+            }
+            _ => commands.entity(bubble_en).despawn(),
+        }
+    }
+
+    for (player_en, ability, player_tf, rai) in player_q.iter() {
+        let has_bubble = bubble_q.iter().any(|(_, bubble, _)| bubble.owner == player_en);
+        if ability.absorbs_shots() && !has_bubble {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: SHIELD_BUBBLE_COLOR,
+                        custom_size: Some(rai.size + Vec2::splat(SHIELD_BUBBLE_MARGIN)),
+                        ..Default::default()
+                    },
+                    transform: Transform {
+                        translation: Vec3::new(player_tf.translation.x, player_tf.translation.y, 11.),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(ShieldBubble { owner: player_en });
+        }
+    }
+}
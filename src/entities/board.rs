@@ -0,0 +1,220 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{Game, WinSize, SpriteInfos, GameState, difficulty::Difficulty, shared::{Health, Projectile}};
+
+use super::{AlienState, AlienType, Boss, BossPart, BossSnapshot, Diving, Enemy, Formation, Obstacle, Players, PowerUp, restore_boss, spawn_block, spawn_fleet, spawn_formation, spawn_obstacles};
+
+/// Sent when alternating play hands the board over to another player
+pub struct TurnChanged {
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AlienSnapshot {
+    pub alien_type: AlienType,
+    /// Slot in the formation
+    pub position: Vec2,
+    pub health: Health,
+    /// `None` while in formation
+    pub diving: Option<DiverSnapshot>,
+}
+
+/// An alien caught mid dive
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DiverSnapshot {
+    pub diving: Diving,
+    /// Where the alien is, relative to the formation
+    pub position: Vec3,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BlockSnapshot {
+    pub position: Vec3,
+    pub health: Health,
+}
+
+/// Everything needed to put a player's board back the way they left it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BoardSnapshot {
+    pub wave: u32,
+    pub alien_state: AlienState,
+    pub formation: Vec3,
+    pub aliens: Vec<AlienSnapshot>,
+    pub obstacles: Vec<BlockSnapshot>,
+    pub boss: Option<BossSnapshot>,
+}
+
+/// Everything that makes up a board, to snapshot or clear it
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub struct BoardQuery<'w, 's> {
+    formation_q: Query<'w, 's, (Entity, &'static Transform), With<Formation>>,
+    alien_q: Query<'w, 's, (Entity, &'static AlienType, &'static Health, &'static Transform, Option<&'static Diving>), With<Enemy>>,
+    obstacle_q: Query<'w, 's, (Entity, &'static Health, &'static Transform), With<Obstacle>>,
+    boss_q: Query<'w, 's, (Entity, &'static Boss, &'static Transform)>,
+    part_q: Query<'w, 's, (Entity, &'static BossPart, &'static Health)>,
+}
+impl<'w, 's> BoardQuery<'w, 's> {
+    /// Board in play, divers are counted back in their slot unless
+    /// `keep_divers`
+    pub fn snapshot(&self, wave: u32, alien_state: &AlienState, keep_divers: bool) -> BoardSnapshot {
+        BoardSnapshot {
+            wave,
+            alien_state: alien_state.clone(),
+            formation: self.formation_q
+                .iter()
+                .next()
+                .map(|(_, tf)| tf.translation)
+                .unwrap_or(Vec3::ZERO),
+            aliens: self.alien_q
+                .iter()
+                .map(|(_, alien_type, health, tf, diving)| AlienSnapshot {
+                    alien_type: *alien_type,
+                    position: diving
+                        .map(|diving| diving.slot)
+                        .unwrap_or(tf.translation.truncate()),
+                    health: health.clone(),
+                    diving: diving
+                        .filter(|_| keep_divers)
+                        .map(|diving| DiverSnapshot {
+                            diving: diving.clone(),
+                            position: tf.translation,
+                        }),
+                })
+                .collect(),
+            obstacles: self.obstacle_q
+                .iter()
+                .map(|(_, health, tf)| BlockSnapshot {
+                    position: tf.translation,
+                    health: health.clone(),
+                })
+                .collect(),
+            boss: self.boss_q.iter().next().map(|(boss_en, boss, tf)| {
+                let parts = self.part_q
+                    .iter()
+                    .filter(|(_, part, _)| part.boss == boss_en)
+                    .map(|(_, part, health)| (part, health));
+                BossSnapshot::new(boss, tf.translation, parts)
+            }),
+        }
+    }
+
+    /// Enemies in the order of the snapshot, its aliens then its boss parts
+    pub fn enemies(&self) -> Vec<Entity> {
+        let boss_en = self.boss_q.iter().next().map(|(boss_en, ..)| boss_en);
+        self.alien_q
+            .iter()
+            .map(|(entity, ..)| entity)
+            .chain(
+                self.part_q
+                    .iter()
+                    .filter(|(_, part, _)| Some(part.boss) == boss_en)
+                    .map(|(entity, ..)| entity)
+            )
+            .collect()
+    }
+
+    /// Every entity of the board
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.alien_q
+            .iter()
+            .map(|(entity, ..)| entity)
+            .chain(self.formation_q.iter().map(|(entity, _)| entity))
+            .chain(self.obstacle_q.iter().map(|(entity, ..)| entity))
+            .chain(self.boss_q.iter().map(|(entity, ..)| entity))
+            .chain(self.part_q.iter().map(|(entity, ..)| entity))
+    }
+}
+
+pub struct BoardPlugin;
+impl Plugin for BoardPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<TurnChanged>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(swap_boards.after("player_lives"))
+            )
+        ;
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn swap_boards(
+    mut commands: Commands,
+    mut events: EventReader<TurnChanged>,
+    mut players: ResMut<Players>,
+    mut game: ResMut<Game>,
+    mut alien_state: ResMut<AlienState>,
+    board_q: BoardQuery,
+    projectile_q: Query<Entity, Or<(With<Projectile>, With<PowerUp>)>>,
+    difficulty: Res<Difficulty>,
+    win_size: Res<WinSize>,
+    sprite_infos: Res<SpriteInfos>,
+) {
+    // Only the latest hand over matters if several arrive at once
+    let turn = match events.iter().last() {
+        Some(turn) => turn,
+        None => return,
+    };
+
+    let snapshot = board_q.snapshot(game.wave, &alien_state, false);
+    players.slots[turn.from].board = Some(snapshot);
+
+    for entity in board_q.entities().chain(projectile_q.iter()) {
+        commands.entity(entity).despawn();
+    }
+
+    match players.slots[turn.to].board.take() {
+        Some(board) => {
+            game.wave = board.wave;
+            *alien_state = board.alien_state.clone();
+            restore_board(&mut commands, board, &sprite_infos);
+        }
+        // First turn of this player, start them on a fresh board
+        None => {
+            game.wave = 1;
+            *alien_state = AlienState::for_wave(game.wave, &difficulty);
+            spawn_fleet(&mut commands, &sprite_infos);
+            spawn_obstacles(&mut commands, &win_size);
+        }
+    }
+}
+
+/// Spawns the aliens, obstacles and boss of `board` on an empty board,
+/// returning its enemies in the order of `BoardQuery::enemies`
+pub fn restore_board(
+    commands: &mut Commands,
+    board: BoardSnapshot,
+    sprite_infos: &SpriteInfos,
+) -> Vec<Entity> {
+    let mut enemies = Vec::new();
+    if !board.aliens.is_empty() {
+        let aliens = board.aliens
+            .iter()
+            .map(|alien| (alien.position, alien.alien_type))
+            .collect::<Vec<_>>();
+        let (_, entities) = spawn_formation(commands, board.formation, &aliens, sprite_infos);
+        for (&entity, alien) in entities.iter().zip(board.aliens) {
+            commands.entity(entity).insert(alien.health);
+            if let Some(diver) = alien.diving {
+                commands
+                    .entity(entity)
+                    .insert(diver.diving)
+                    .insert(Transform::from_translation(diver.position))
+                    .insert(GlobalTransform::from_translation(board.formation + diver.position));
+            }
+        }
+        enemies.extend(entities);
+    }
+    for block in board.obstacles {
+        let entity = spawn_block(commands, block.position.x, block.position.y);
+        commands.entity(entity).insert(block.health);
+    }
+    if let Some(boss) = board.boss {
+        enemies.extend(restore_boss(commands, boss, sprite_infos));
+    }
+    enemies
+}
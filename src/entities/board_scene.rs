@@ -0,0 +1,125 @@
+use bevy::{prelude::*, scene::DynamicEntity};
+
+use crate::{GameState, WinSize, SpriteInfos, savegame::PendingLoad, shared::Health};
+
+use super::{AlienType, Obstacle, PlayerState, Players, player_row, spawn_block, spawn_formation, spawn_player};
+
+/// Board set up from a `.scn.ron` scene in place of the procedural fleet,
+/// bunkers and ships. Every scene entity with a `Transform` and an
+/// `AlienType`, `Obstacle` or `PlayerState` is put on the board there, with
+/// its `Health` if it has one. Players left out start at their usual spot.
+/// Only the opening board comes from the scene, later waves are procedural.
+/// See `assets/scenes/example_board.scn.ron`.
+#[derive(Default)]
+pub struct BoardScene(pub Option<Handle<DynamicScene>>);
+impl BoardScene {
+    /// Scene given on the command line as `--board <path>`, relative to the
+    /// assets folder
+    pub fn path_from_args() -> Option<String> {
+        let args = std::env::args().collect::<Vec<_>>();
+        args.iter()
+            .position(|arg| arg == "--board")
+            .and_then(|idx| args.get(idx + 1))
+            .map(|path| path.as_str())
+            .or_else(|| args.iter().find_map(|arg| arg.strip_prefix("--board=")))
+            .map(|path| path.to_string())
+    }
+
+    /// Whether the procedural setup should leave the board to the scene
+    pub fn is_set(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+pub struct BoardScenePlugin;
+impl Plugin for BoardScenePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BoardScene>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame)
+                    .with_system(setup_board_scene)
+            )
+        ;
+    }
+}
+
+/// Component of type `T` of a scene entity, if it has one
+fn scene_component<T: Reflect + Default>(entity: &DynamicEntity) -> Option<T> {
+    entity
+        .components
+        .iter()
+        .find(|component| component.type_name() == std::any::type_name::<T>())
+        .map(|component| {
+            let mut value = T::default();
+            value.apply(&**component);
+            value
+        })
+}
+
+fn setup_board_scene(
+    mut commands: Commands,
+    mut players: ResMut<Players>,
+    board_scene: Res<BoardScene>,
+    scenes: Res<Assets<DynamicScene>>,
+    pending_load: Res<PendingLoad>,
+    win_size: Res<WinSize>,
+    sprite_infos: Res<SpriteInfos>,
+) {
+    // The saved board wins over the scene
+    if pending_load.0.is_some() {
+        return;
+    }
+    let scene = match board_scene.0.as_ref().and_then(|handle| scenes.get(handle)) {
+        Some(scene) => scene,
+        None => return,
+    };
+
+    let mut aliens = Vec::new();
+    let mut alien_healths = Vec::new();
+    let mut placed = Vec::new();
+    for entity in scene.entities.iter() {
+        let position = match scene_component::<Transform>(entity) {
+            Some(tf) => tf.translation.truncate(),
+            None => continue,
+        };
+        let health = scene_component::<Health>(entity);
+
+        if let Some(alien_type) = scene_component::<AlienType>(entity) {
+            aliens.push((position, alien_type));
+            alien_healths.push(health);
+        } else if scene_component::<Obstacle>(entity).is_some() {
+            let block = spawn_block(&mut commands, position.x, position.y);
+            if let Some(health) = health {
+                commands.entity(block).insert(health);
+            }
+        } else if let Some(player) = scene_component::<PlayerState>(entity) {
+            let index = player.index;
+            if index >= players.slots.len() || !players.is_playing(index) || placed.contains(&index) {
+                continue;
+            }
+            let ship = spawn_player(&mut commands, &sprite_infos, index, position, &mut players.slots[index]);
+            if let Some(health) = health {
+                commands.entity(ship).insert(health);
+            }
+            placed.push(index);
+        }
+    }
+
+    if !aliens.is_empty() {
+        let (_, alien_ens) = spawn_formation(&mut commands, Vec3::ZERO, &aliens, &sprite_infos);
+        for (alien, health) in alien_ens.into_iter().zip(alien_healths) {
+            if let Some(health) = health {
+                commands.entity(alien).insert(health);
+            }
+        }
+    }
+
+    players.turn = 0;
+    for index in 0..players.slots.len() {
+        if players.is_playing(index) && !placed.contains(&index) {
+            let position = Vec2::new(players.spawn_x(index), player_row(&win_size));
+            spawn_player(&mut commands, &sprite_infos, index, position, &mut players.slots[index]);
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{AssetScaling, GameState, SpriteInfos, WinSize, GAME_TIME_STEP, shared::{Health, RenderedAssetInfo, ScoreValue, Weapon, WeaponKind, fire_weapon}};
+
+use super::{Enemy, FromEnemy, Players};
+
+/// Every this many waves the fleet is replaced by a boss
+const BOSS_WAVE_INTERVAL: u32 = 5;
+const BOSS_CRUISE_Y: f32 = 170.;
+const BOSS_ENTER_SPEED: f32 = 60.;
+const BOSS_SWEEP_SPEED: f32 = 90.;
+/// Share of health left below which the boss stops cycling and enrages
+const BOSS_ENRAGE_AT: f32 = 0.35;
+const BOSS_REWARD_POINTS: u32 = 1000;
+
+pub fn is_boss_wave(wave: u32) -> bool {
+    wave.is_multiple_of(BOSS_WAVE_INTERVAL)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BossPhase {
+    /// Descends into view, can't fire yet
+    Entering,
+    /// Sweeps side to side firing fans at the players
+    Sweep,
+    /// Holds still and fires rings of shots
+    Barrage,
+    /// Everything at once, faster, until it dies
+    Enraged,
+}
+
+/// Controller of a boss, its parts follow it around
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Boss {
+    pub phase: BossPhase,
+    /// Health of all its parts together when it arrived
+    pub max_hp: u32,
+    direction: f32,
+    #[serde(with = "crate::savegame::saved_timer")]
+    phase_timer: Timer,
+    #[serde(with = "crate::savegame::saved_timer")]
+    fire_timer: Timer,
+}
+impl Boss {
+    /// Share of `max_hp` the given remaining parts still have
+    pub fn health_left<'a>(&self, parts: impl Iterator<Item = &'a Health>) -> f32 {
+        let hp = parts.map(|health| health.current_hp).sum::<u32>();
+        if self.max_hp > 0 { hp as f32 / self.max_hp as f32 } else { 0. }
+    }
+
+    fn enter_phase(&mut self, phase: BossPhase) {
+        let (duration, fire_every) = match phase {
+            BossPhase::Entering => (0., 1.),
+            BossPhase::Sweep => (6., 1.2),
+            BossPhase::Barrage => (4., 0.8),
+            BossPhase::Enraged => (0., 0.6),
+        };
+        self.phase = phase;
+        self.phase_timer = Timer::from_seconds(duration, false);
+        self.fire_timer = Timer::from_seconds(fire_every, true);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BossPartKind {
+    Core,
+    Wing,
+}
+impl BossPartKind {
+    fn sprite(&self, sprite_infos: &SpriteInfos) -> (Handle<Image>, Vec2) {
+        let (asset, scale) = match self {
+            BossPartKind::Core => (sprite_infos.top_alien.clone(), 2.5),
+            BossPartKind::Wing => (sprite_infos.red_enemy.clone(), 1.5),
+        };
+        (asset.0, asset.1 * scale)
+    }
+}
+
+/// A separately hittable segment of a boss
+#[derive(Component)]
+pub struct BossPart {
+    pub boss: Entity,
+    pub kind: BossPartKind,
+    /// Position relative to the boss
+    offset: Vec2,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BossPartSnapshot {
+    pub kind: BossPartKind,
+    pub offset: Vec2,
+    pub health: Health,
+}
+
+/// A boss and the parts it has left, to put it back mid fight
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BossSnapshot {
+    pub boss: Boss,
+    pub position: Vec3,
+    pub parts: Vec<BossPartSnapshot>,
+}
+impl BossSnapshot {
+    pub fn new<'a>(boss: &Boss, position: Vec3, parts: impl Iterator<Item = (&'a BossPart, &'a Health)>) -> Self {
+        Self {
+            boss: boss.clone(),
+            position,
+            parts: parts
+                .map(|(part, health)| BossPartSnapshot {
+                    kind: part.kind,
+                    offset: part.offset,
+                    health: health.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+pub struct BossPlugin;
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(update_boss_phase.label("boss_phase").label("movement").after("player_shooting"))
+                    .with_system(move_boss_parts.label("movement").after("boss_phase"))
+                    .with_system(boss_attack.after("fleet_fire"))
+                    .with_system(check_boss_defeated.after("collisions"))
+            )
+        ;
+    }
+}
+
+pub fn spawn_boss(commands: &mut Commands, wave: u32, win_size: &WinSize, sprite_infos: &SpriteInfos) {
+    let start = Vec3::new(0., win_size.h / 2. + 60., 5.);
+
+    // Later bosses soak up more punishment
+    let toughness = wave / BOSS_WAVE_INTERVAL;
+    let parts = [
+        (BossPartKind::Core, Vec2::ZERO, 16 + 6 * toughness),
+        (BossPartKind::Wing, Vec2::new(-70., -10.), 6 + 2 * toughness),
+        (BossPartKind::Wing, Vec2::new(70., -10.), 6 + 2 * toughness),
+    ];
+
+    let mut boss = Boss {
+        phase: BossPhase::Entering,
+        max_hp: parts.iter().map(|(_, _, hp)| hp).sum(),
+        direction: 1.,
+        phase_timer: Timer::from_seconds(0., false),
+        fire_timer: Timer::from_seconds(1., true),
+    };
+    boss.enter_phase(BossPhase::Entering);
+
+    let boss_en = commands
+        .spawn()
+        .insert(boss)
+        .insert(Transform::from_translation(start))
+        .id();
+
+    for (kind, offset, hp) in parts {
+        spawn_boss_part(commands, boss_en, start, kind, offset, Health::from(hp), sprite_infos);
+    }
+}
+
+/// Spawns the boss of `snapshot`, returning its parts in the snapshot order
+pub fn restore_boss(commands: &mut Commands, snapshot: BossSnapshot, sprite_infos: &SpriteInfos) -> Vec<Entity> {
+    let boss_en = commands
+        .spawn()
+        .insert(snapshot.boss)
+        .insert(Transform::from_translation(snapshot.position))
+        .id();
+
+    snapshot.parts
+        .into_iter()
+        .map(|part| spawn_boss_part(commands, boss_en, snapshot.position, part.kind, part.offset, part.health, sprite_infos))
+        .collect()
+}
+
+fn spawn_boss_part(
+    commands: &mut Commands,
+    boss_en: Entity,
+    boss_position: Vec3,
+    kind: BossPartKind,
+    offset: Vec2,
+    health: Health,
+    sprite_infos: &SpriteInfos,
+) -> Entity {
+    let (texture, asset_size) = kind.sprite(sprite_infos);
+    let translation = boss_position + offset.extend(0.);
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(asset_size),
+                ..Default::default()
+            },
+            texture,
+            transform: Transform::from_translation(translation),
+            // Hit tests read it before the transforms are next propagated
+            global_transform: GlobalTransform::from_translation(translation),
+            ..Default::default()
+        })
+        .insert(Enemy)
+        .insert(BossPart { boss: boss_en, kind, offset })
+        .insert(health)
+        .insert(RenderedAssetInfo::new(asset_size))
+        .insert(ScoreValue { points: 50 })
+        .id()
+}
+
+/// Phase state machine: enter, then alternate sweep and barrage until
+/// health drops low enough to enrage.
+fn update_boss_phase(
+    mut q: Query<(Entity, &mut Boss, &mut Transform)>,
+    part_q: Query<(&BossPart, &Health)>,
+    time: Res<Time>,
+    win_size: Res<WinSize>,
+) {
+    for (boss_en, mut boss, mut tf) in q.iter_mut() {
+        let health_left = boss.health_left(
+            part_q
+                .iter()
+                .filter(|(part, _)| part.boss == boss_en)
+                .map(|(_, health)| health)
+        );
+
+        boss.phase_timer.tick(time.delta());
+        match boss.phase {
+            BossPhase::Entering => {
+                tf.translation.y -= BOSS_ENTER_SPEED * GAME_TIME_STEP;
+                if tf.translation.y <= BOSS_CRUISE_Y {
+                    tf.translation.y = BOSS_CRUISE_Y;
+                    boss.enter_phase(BossPhase::Sweep);
+                }
+            }
+            BossPhase::Enraged => {}
+            _ if health_left <= BOSS_ENRAGE_AT => boss.enter_phase(BossPhase::Enraged),
+            BossPhase::Sweep if boss.phase_timer.finished() => boss.enter_phase(BossPhase::Barrage),
+            BossPhase::Barrage if boss.phase_timer.finished() => boss.enter_phase(BossPhase::Sweep),
+            _ => {}
+        }
+
+        let speed = match boss.phase {
+            BossPhase::Sweep => BOSS_SWEEP_SPEED,
+            BossPhase::Enraged => BOSS_SWEEP_SPEED * 1.6,
+            _ => 0.,
+        };
+        let bound = win_size.w / 2. - 120.;
+        tf.translation.x += boss.direction * speed * GAME_TIME_STEP;
+        if tf.translation.x.abs() >= bound {
+            tf.translation.x = tf.translation.x.clamp(-bound, bound);
+            boss.direction *= -1.;
+        }
+    }
+}
+
+fn move_boss_parts(
+    boss_q: Query<&Transform, With<Boss>>,
+    mut part_q: Query<(&BossPart, &mut Transform), Without<Boss>>,
+) {
+    for (part, mut tf) in part_q.iter_mut() {
+        if let Ok(boss_tf) = boss_q.get(part.boss) {
+            tf.translation = boss_tf.translation + part.offset.extend(0.);
+        }
+    }
+}
+
+fn boss_attack(
+    mut commands: Commands,
+    mut q: Query<(Entity, &mut Boss)>,
+    part_q: Query<(&BossPart, &Transform)>,
+    time: Res<Time>,
+    sprite_infos: Res<SpriteInfos>,
+    asset_scaling: Res<AssetScaling>,
+) {
+    for (boss_en, mut boss) in q.iter_mut() {
+        if boss.phase == BossPhase::Entering || !boss.fire_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        for (part, tf) in part_q.iter().filter(|(part, _)| part.boss == boss_en) {
+            let weapon = match (boss.phase, part.kind) {
+                (BossPhase::Sweep, BossPartKind::Wing) => Some(boss_fan(3, 0.6)),
+                (BossPhase::Sweep, BossPartKind::Core) => None,
+                (BossPhase::Barrage, BossPartKind::Core) => Some(boss_ring(12)),
+                (BossPhase::Barrage, BossPartKind::Wing) => None,
+                (BossPhase::Enraged, BossPartKind::Core) => Some(boss_ring(16)),
+                (BossPhase::Enraged, BossPartKind::Wing) => Some(boss_fan(5, 0.9)),
+                (BossPhase::Entering, _) => None,
+            };
+
+            if let Some(weapon) = weapon {
+                let projectiles = fire_weapon(
+                    &mut commands,
+                    &weapon,
+                    tf.translation,
+                    -1.,
+                    &sprite_infos.alien_laser,
+                    asset_scaling.enemy_projectile,
+                    0.,
+                );
+                for projectile in projectiles {
+                    commands.entity(projectile).insert(FromEnemy);
+                }
+            }
+        }
+    }
+}
+
+fn boss_fan(count: u32, spread: f32) -> Weapon {
+    Weapon {
+        projectile_count: count,
+        spread,
+        projectile_speed: 200.,
+        ..Weapon::new(WeaponKind::Spread)
+    }
+}
+
+/// Shots spread evenly all the way around
+fn boss_ring(count: u32) -> Weapon {
+    Weapon {
+        projectile_count: count,
+        spread: 2. * PI * (count - 1) as f32 / count as f32,
+        projectile_speed: 140.,
+        ..Weapon::new(WeaponKind::Spread)
+    }
+}
+
+/// Removes bosses without parts left and rewards everyone on the board
+fn check_boss_defeated(
+    mut commands: Commands,
+    mut players: ResMut<Players>,
+    q: Query<Entity, With<Boss>>,
+    part_q: Query<&BossPart>,
+) {
+    for boss_en in q.iter() {
+        if part_q.iter().any(|part| part.boss == boss_en) {
+            continue;
+        }
+
+        commands.entity(boss_en).despawn();
+
+        for index in 0..players.slots.len() {
+            if players.is_playing(index) {
+                let slot = &mut players.slots[index];
+                slot.score += BOSS_REWARD_POINTS;
+                slot.lives += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use bevy::{prelude::*, reflect::TypeUuid};
+use rand::prelude::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::{AssetScaling, DataInfos, GameRng, GameState, difficulty::Difficulty, SpriteInfos, WinSize, GAME_TIME_STEP, shared::{WeaponState, fire_weapon}};
+
+use super::{AlienType, Enemy, Formation, FromEnemy, Player};
+
+const DIVE_RETURN_SPEED: f32 = 160.;
+const DIVE_FIRE_INTERVAL: f32 = 0.9;
+
+/// One dive, see `assets/data/dive_paths.dives.ron`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DivePath {
+    /// Seconds taken to fly the whole path
+    pub duration: f32,
+    /// Cubic Bezier segments, offsets from the formation slot
+    pub segments: Vec<[(f32, f32); 4]>,
+}
+impl DivePath {
+    /// Offset from the slot at `t` between 0 and 1, each segment taking
+    /// an equal share of the dive
+    fn point(&self, t: f32) -> Vec2 {
+        if self.segments.is_empty() {
+            return Vec2::ZERO;
+        }
+
+        let scaled = t.clamp(0., 1.) * self.segments.len() as f32;
+        let idx = (scaled as usize).min(self.segments.len() - 1);
+        let t = scaled - idx as f32;
+        let [p0, p1, p2, p3] = self.segments[idx].map(|(x, y)| Vec2::new(x, y));
+
+        let u = 1. - t;
+        p0 * u * u * u + p1 * 3. * u * u * t + p2 * 3. * u * t * t + p3 * t * t * t
+    }
+}
+
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "6f1c6a0e-3d0b-4f3e-9d55-2b8f8d1f7c41"]
+pub struct DivePaths {
+    pub paths: Vec<DivePath>,
+}
+
+/// Galaga style attacks where aliens leave the fleet to dive at the players
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DiveAttacks {
+    pub enabled: bool,
+    /// Time between two aliens breaking formation
+    #[serde(with = "crate::savegame::saved_timer")]
+    pub timer: Timer,
+    pub max_divers: usize,
+}
+impl Default for DiveAttacks {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timer: Timer::from_seconds(4., true),
+            max_divers: 2,
+        }
+    }
+}
+
+/// An alien out of formation, flying relative to its formation
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Diving {
+    /// Where the alien goes back to, relative to the formation
+    pub slot: Vec2,
+    path: DivePath,
+    elapsed: f32,
+    /// -1 to mirror the path toward a player on the left
+    mirror: f32,
+    /// Shift spread over the dive so it ends above the player
+    aim: f32,
+    returning: bool,
+    #[serde(with = "crate::savegame::saved_timer")]
+    fire_timer: Timer,
+}
+impl Diving {
+    fn offset(&self, t: f32) -> Vec2 {
+        let point = self.path.point(t);
+        Vec2::new(point.x * self.mirror + self.aim * t, point.y)
+    }
+}
+
+pub struct DivePlugin;
+impl Plugin for DivePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<DiveAttacks>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(launch_dives.label("launch_dives").after("fleet_fire"))
+                    // Follows the formation it flies out of
+                    .with_system(fly_dives.label("movement").after("formation_vertical"))
+            )
+        ;
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn launch_dives(
+    mut commands: Commands,
+    mut dive_attacks: ResMut<DiveAttacks>,
+    alien_q: Query<(Entity, &Transform, &GlobalTransform), (With<AlienType>, Without<Diving>)>,
+    diving_q: Query<(), With<Diving>>,
+    player_q: Query<&Transform, With<Player>>,
+    time: Res<Time>,
+    difficulty: Res<Difficulty>,
+    data_infos: Res<DataInfos>,
+    dive_paths: Res<Assets<DivePaths>>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    let interval = std::time::Duration::from_secs_f32(difficulty.dive_interval);
+    if dive_attacks.timer.duration() != interval {
+        dive_attacks.timer.set_duration(interval);
+    }
+    if !dive_attacks.enabled || !dive_attacks.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    if diving_q.iter().count() >= dive_attacks.max_divers {
+        return;
+    }
+
+    let rng = &mut game_rng.0;
+    let path = dive_paths
+        .get(&data_infos.dive_paths)
+        .and_then(|paths| paths.paths.choose(rng));
+    // Picked in board order rather than query order, so a loaded game
+    // sends the same aliens diving
+    let mut players = player_q.iter().collect::<Vec<_>>();
+    players.sort_by(|a, b| a.translation.x.total_cmp(&b.translation.x));
+    let mut aliens = alien_q.iter().collect::<Vec<_>>();
+    aliens.sort_by(|(_, _, a), (_, _, b)| {
+        a.translation.x.total_cmp(&b.translation.x).then(a.translation.y.total_cmp(&b.translation.y))
+    });
+
+    if let (Some(path), Some(player_tf), Some((alien_en, alien_tf, alien_gtf))) =
+        (path, players.choose(rng), aliens.choose(rng))
+    {
+        let start_x = alien_gtf.translation.x;
+        let mirror = if player_tf.translation.x < start_x { -1. } else { 1. };
+        let end = path.point(1.);
+
+        commands.entity(*alien_en).insert(Diving {
+            slot: alien_tf.translation.truncate(),
+            path: path.clone(),
+            elapsed: 0.,
+            mirror,
+            aim: player_tf.translation.x - (start_x + end.x * mirror),
+            returning: false,
+            fire_timer: Timer::from_seconds(DIVE_FIRE_INTERVAL, true),
+        });
+    }
+}
+
+/// Flies divers along their path, firing on the way down, then back into
+/// their slot. Divers leaving the bottom of the screen come back from the top.
+#[allow(clippy::type_complexity)]
+fn fly_dives(
+    mut commands: Commands,
+    mut q: Query<(Entity, &Parent, &mut Diving, &mut Transform, &GlobalTransform, &WeaponState), With<Enemy>>,
+    formation_q: Query<&Transform, (With<Formation>, Without<Enemy>)>,
+    time: Res<Time>,
+    win_size: Res<WinSize>,
+    sprite_infos: Res<SpriteInfos>,
+    asset_scaling: Res<AssetScaling>,
+) {
+    for (entity, parent, mut diving, mut tf, gtf, weapon_state) in q.iter_mut() {
+        let formation_tf = match formation_q.get(parent.0) {
+            Ok(formation_tf) => formation_tf,
+            Err(_) => continue,
+        };
+
+        if !diving.returning {
+            diving.elapsed += GAME_TIME_STEP;
+            let t = diving.elapsed / diving.path.duration.max(GAME_TIME_STEP);
+            let position = diving.slot + diving.offset(t);
+            tf.translation.x = position.x;
+            tf.translation.y = position.y;
+
+            if diving.fire_timer.tick(time.delta()).just_finished() {
+                let projectiles = fire_weapon(
+                    &mut commands,
+                    &weapon_state.weapon,
+                    gtf.translation,
+                    -1.,
+                    &sprite_infos.alien_laser,
+                    asset_scaling.enemy_projectile,
+                    0.,
+                );
+                for projectile in projectiles {
+                    commands.entity(projectile).insert(FromEnemy);
+                }
+            }
+
+            if t >= 1. {
+                diving.returning = true;
+                if formation_tf.translation.y + tf.translation.y < -win_size.h / 2. {
+                    tf.translation.x = diving.slot.x;
+                    tf.translation.y = win_size.h / 2. + 20. - formation_tf.translation.y;
+                }
+            }
+            continue;
+        }
+
+        let to_slot = diving.slot - tf.translation.truncate();
+        let step = DIVE_RETURN_SPEED * GAME_TIME_STEP;
+        if to_slot.length() <= step {
+            tf.translation.x = diving.slot.x;
+            tf.translation.y = diving.slot.y;
+            commands.entity(entity).remove::<Diving>();
+        } else {
+            let moved = to_slot.normalize() * step;
+            tf.translation.x += moved.x;
+            tf.translation.y += moved.y;
+        }
+    }
+}
@@ -1,152 +1,397 @@
-use std::{collections::HashSet, f32::consts::PI};
+use std::collections::{HashMap, HashSet};
 
-use bevy::{prelude::*, sprite::{self, collide_aabb::collide}, core::FixedTimestep};
+use bevy::{prelude::*, sprite::collide_aabb::collide};
 use rand::prelude::SliceRandom;
-use crate::{Game, WinSize, SpriteInfos, shared::{Health, RenderedAssetInfo, WeaponState, Velocity, MovementSpeed, Projectile}, AssetScaling, GAME_TIME_STEP};
+use serde::{Deserialize, Serialize};
+use crate::{Game, GameRng, GameState, WinSize, difficulty::Difficulty, savegame::PendingLoad, settings::Settings, SpriteInfos, shared::{Health, RenderedAssetInfo, WeaponState, MovementSpeed, Motion, MotionPattern, Projectile, ScoreValue, Weapon, WeaponKind, fire_weapon, spawn_explosion}, AssetScaling, GAME_TIME_STEP};
 
-use super::Obstacle;
+use super::{BoardScene, Obstacle, Player, is_boss_wave, spawn_boss};
 
-enum AlienType {
+/// Horizontal distance between two columns of the fleet
+const FLEET_COLUMN_WIDTH: f32 = 60.;
+/// Reload time kept from one wave to the next
+const FLEET_RELOAD_PER_WAVE: f32 = 0.95;
+/// Shortest reload whatever the wave and survivors
+const FLEET_MIN_RELOAD: f32 = 0.25;
+
+// Variant names are the ones written in board scenes and saves
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect_value(Component, PartialEq, Serialize, Deserialize)]
+pub enum AlienType {
+    #[default]
     RED,
     GREEN,
     YELLOW,
 }
 
-#[derive(Component)]
-pub struct FromEnemy;
+/// Stats and sprite of every kind of alien, higher rows are tougher and worth
+/// more
+const ALIEN_ARCHETYPES: [(AlienType, AlienArchetype); 3] = [
+    (AlienType::RED, AlienArchetype {
+        hp: 1,
+        points: 10,
+        shot: AlienShotKind::Plunger,
+        fire_weight: 3,
+        death_effect: DeathEffect::Explosion,
+        sprite: |sprite_infos| &sprite_infos.red_enemy,
+    }),
+    (AlienType::GREEN, AlienArchetype {
+        hp: 1,
+        points: 20,
+        shot: AlienShotKind::Squiggly,
+        fire_weight: 2,
+        death_effect: DeathEffect::Explosion,
+        sprite: |sprite_infos| &sprite_infos.green_enemy,
+    }),
+    (AlienType::YELLOW, AlienArchetype {
+        hp: 2,
+        points: 30,
+        shot: AlienShotKind::Rolling,
+        fire_weight: 1,
+        death_effect: DeathEffect::Burst { shots: 3 },
+        sprite: |sprite_infos| &sprite_infos.yellow_enemy,
+    }),
+];
 
-#[derive(Component)]
-pub struct Enemy;
+impl AlienType {
+    pub fn archetype(&self) -> AlienArchetype {
+        ALIEN_ARCHETYPES.iter()
+            .find(|(alien_type, _)| alien_type == self)
+            .map(|(_, archetype)| *archetype)
+            .expect("every alien type has an archetype")
+    }
 
-#[derive(Component)]
-pub struct EnemyState;
-
-#[derive(Bundle)]
-struct AlienBundle {
-    #[bundle]
-    _sb: SpriteBundle,
-    _e: Enemy,
-    _h: Health,
-    _ws: WeaponState,
-    _rai: RenderedAssetInfo,
-}
-impl AlienBundle {
-    fn new(x: f32, y: f32, alien_type: AlienType, sprite_infos: &Res<SpriteInfos>) -> Self {
-        let asset = match alien_type {
-            AlienType::RED => sprite_infos.red_enemy.clone(),
-            AlienType::GREEN => sprite_infos.green_enemy.clone(),
-            AlienType::YELLOW => sprite_infos.yellow_enemy.clone(),
-        };
+    fn sprite(&self, sprite_infos: &SpriteInfos) -> (Handle<Image>, Vec2) {
+        (self.archetype().sprite)(sprite_infos).clone()
+    }
+}
 
-        let asset_size = Vec2::new (
-            1. * asset.1.x,
-            1. * asset.1.y,
-        );
-        let asset_info = RenderedAssetInfo::new(asset_size);
+#[derive(Clone, Copy, Debug)]
+pub struct AlienArchetype {
+    pub hp: u32,
+    pub points: u32,
+    pub shot: AlienShotKind,
+    /// Relative chance of being picked when the fleet fires
+    pub fire_weight: u32,
+    pub death_effect: DeathEffect,
+    /// Picks the loaded sprite of this kind of alien
+    pub sprite: fn(&SpriteInfos) -> &(Handle<Image>, Vec2),
+}
 
-        Self {
-            _sb: SpriteBundle {
-                texture: asset.0,
-                transform: Transform {
-                    translation: Vec3::new(x, y, 5.),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            _e: Enemy,
-            _h: Health::default(),
-            _ws: WeaponState::fast_normal_weapon(),
-            _rai: asset_info,
-        }
-    }
+/// What happens where an alien is shot down
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeathEffect {
+    Explosion,
+    /// Explodes into a fan of shots aimed down
+    Burst { shots: u32 },
 }
 
-#[derive(Bundle)]
-struct TopAlienBundle {
-    #[bundle]
-    _sb: SpriteBundle,
-    _e: Enemy,
-    _h: Health,
-    _ws: WeaponState,
-    _rai: RenderedAssetInfo
-} 
-impl TopAlienBundle {
-    fn new(x: f32, y: f32, sprite_infos: &Res<SpriteInfos>) -> Self {
-        let asset = sprite_infos.top_alien.clone();
-        let asset_size = Vec2::new (
-            1. * asset.1.x,
-            1. * asset.1.y,
-        );
-        let asset_info = RenderedAssetInfo::new(asset_size);
+/// The three shots of the original cabinet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlienShotKind {
+    /// Dropped from the column above the player
+    Rolling,
+    /// Plain straight shot
+    Plunger,
+    /// Slow shot swaying from side to side
+    Squiggly,
+}
+impl AlienShotKind {
+    /// Order the fleet cycles through its shots in
+    pub const ROTATION: [AlienShotKind; 3] = [
+        AlienShotKind::Rolling,
+        AlienShotKind::Plunger,
+        AlienShotKind::Squiggly,
+    ];
 
-        Self {
-            _sb: SpriteBundle {
-                texture: asset.0,
-                transform: Transform {
-                    translation: Vec3::new(x, y, 5.),
-                    ..Default::default()
-                },
-                ..Default::default()
+    pub fn profile(&self) -> ShotProfile {
+        match self {
+            AlienShotKind::Rolling => ShotProfile {
+                speed: 280.,
+                scale: Vec2::new(1., 1.),
+                tint: Color::rgb(1., 1., 0.5),
+                pattern: MotionPattern::ZigZag { amplitude: 2., interval: 0.05 },
+                tracks_player: true,
+                strength: 2,
+            },
+            AlienShotKind::Plunger => ShotProfile {
+                speed: 220.,
+                scale: Vec2::new(1.4, 0.8),
+                tint: Color::rgb(1., 0.6, 0.4),
+                pattern: MotionPattern::Linear,
+                tracks_player: false,
+                strength: 1,
+            },
+            AlienShotKind::Squiggly => ShotProfile {
+                speed: 160.,
+                scale: Vec2::new(1., 1.2),
+                tint: Color::rgb(0.5, 1., 0.6),
+                pattern: MotionPattern::Sine { amplitude: 10., frequency: 2. },
+                tracks_player: false,
+                strength: 0,
             },
-            _e: Enemy,
-            _h: Health::default(),
-            _ws: WeaponState::fast_normal_weapon(),
-            _rai: asset_info,
         }
     }
 }
 
+/// How the shots of one kind of alien look and fly
+#[derive(Clone, Copy, Debug)]
+pub struct ShotProfile {
+    pub speed: f32,
+    /// Relative to the enemy projectile scaling
+    pub scale: Vec2,
+    pub tint: Color,
+    pub pattern: MotionPattern,
+    pub tracks_player: bool,
+    /// Against player shots, which have a strength of 1
+    pub strength: u32,
+}
+
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct FromEnemy;
+
+/// Sent whenever an alien is shot down
+pub struct EnemyDestroyed {
+    pub position: Vec3,
+    /// `None` for enemies outside the fleet
+    pub alien_type: Option<AlienType>,
+}
+
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Enemy;
+
+/// Spawns one alien with the stats of its archetype, `x` and `y` being
+/// relative to its formation at `origin`
+fn spawn_alien(commands: &mut Commands, origin: Vec3, x: f32, y: f32, alien_type: AlienType, sprite_infos: &SpriteInfos) -> Entity {
+    let asset = alien_type.sprite(sprite_infos);
+    let archetype = alien_type.archetype();
+
+    let asset_size = Vec2::new (
+        1. * asset.1.x,
+        1. * asset.1.y,
+    );
+    let asset_info = RenderedAssetInfo::new(asset_size);
+
+    let profile = archetype.shot.profile();
+    let mut weapon_state = WeaponState::fast_normal_weapon();
+    weapon_state.weapon.projectile_speed = profile.speed;
+    weapon_state.weapon.strength = profile.strength;
+
+    let translation = Vec3::new(x, y, 5.);
+    commands
+        .spawn_bundle(SpriteBundle {
+            texture: asset.0,
+            transform: Transform::from_translation(translation),
+            // Hit tests read it before the transforms are next propagated
+            global_transform: GlobalTransform::from_translation(origin + translation),
+            ..Default::default()
+        })
+        .insert(Enemy)
+        .insert(Health::from(archetype.hp))
+        .insert(weapon_state)
+        .insert(asset_info)
+        .insert(ScoreValue { points: archetype.points })
+        .insert(alien_type)
+        .id()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AlienState {
     movement_direction: f32,
     movement_speed: MovementSpeed,
     move_down: bool,
+    /// How far the fleet drops each time it reaches an edge
+    pub descent_step: f32,
     available_to_shoot: u32,
 }
+impl AlienState {
+    /// Each new wave marches a little faster than the last
+    pub fn for_wave(wave: u32, difficulty: &Difficulty) -> Self {
+        let speed = difficulty.fleet_speed + difficulty.fleet_speed_per_wave * wave.saturating_sub(1) as f32;
+        Self {
+            movement_direction: -1.,
+            movement_speed: MovementSpeed { value: speed },
+            move_down: false,
+            descent_step: difficulty.descent_step,
+            available_to_shoot: difficulty.shooters,
+        }
+    }
+}
+
+/// Decides when the fleet fires, how much and with which shot
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FleetFire {
+    #[serde(with = "crate::savegame::saved_timer")]
+    reload: Timer,
+    /// Wave the fleet size below was taken in
+    wave: u32,
+    /// Aliens at the start of the wave, the fewer are left the faster they reload
+    fleet_size: usize,
+    /// Index into `AlienShotKind::ROTATION` of the next shot
+    next_shot: usize,
+}
+impl Default for FleetFire {
+    fn default() -> Self {
+        Self {
+            reload: Timer::from_seconds(1., false),
+            wave: 0,
+            fleet_size: 0,
+            next_shot: 0,
+        }
+    }
+}
+impl FleetFire {
+    /// Seconds between two volleys, shorter on later waves and as the fleet
+    /// thins out
+    fn reload_secs(&self, difficulty: &Difficulty, wave: u32, survivors: usize) -> f32 {
+        let wave_factor = FLEET_RELOAD_PER_WAVE.powi(wave.saturating_sub(1) as i32);
+        let survivor_factor = if self.fleet_size == 0 {
+            1.
+        } else {
+            0.5 + 0.5 * survivors as f32 / self.fleet_size as f32
+        };
+        (difficulty.fire_interval * wave_factor * survivor_factor).max(FLEET_MIN_RELOAD)
+    }
+
+    /// Next shot in the rotation some alien left can fire, skipping the
+    /// kinds whose aliens are all gone
+    fn take_shot(&mut self, available: impl Fn(AlienShotKind) -> bool) -> Option<AlienShotKind> {
+        let rotation = AlienShotKind::ROTATION;
+        let found = (0..rotation.len())
+            .map(|step| (self.next_shot + step) % rotation.len())
+            .find(|&idx| available(rotation[idx]))?;
+        self.next_shot = (found + 1) % rotation.len();
+        Some(rotation[found])
+    }
+}
+
+/// Parent of a fleet of aliens, moving it moves every alien in it
+#[derive(Component)]
+pub struct Formation {
+    /// Area covered by the aliens left, relative to the formation
+    pub min: Vec2,
+    pub max: Vec2,
+    /// Slot and half size of every alien in the formation
+    members: HashMap<Entity, (Vec2, Vec2)>,
+}
+impl Default for Formation {
+    fn default() -> Self {
+        Self {
+            min: Vec2::splat(f32::MAX),
+            max: Vec2::splat(f32::MIN),
+            members: HashMap::new(),
+        }
+    }
+}
+impl Formation {
+    fn add(&mut self, entity: Entity, position: Vec2, half_size: Vec2) {
+        self.members.insert(entity, (position, half_size));
+        self.min = self.min.min(position - half_size);
+        self.max = self.max.max(position + half_size);
+    }
+
+    /// Bounds only need recomputing when an alien on the edge is gone
+    fn remove(&mut self, entity: Entity) {
+        if let Some((position, half_size)) = self.members.remove(&entity) {
+            let low = position - half_size;
+            let high = position + half_size;
+            if low.x <= self.min.x || low.y <= self.min.y || high.x >= self.max.x || high.y >= self.max.y {
+                self.recompute_bounds();
+            }
+        }
+    }
+
+    fn recompute_bounds(&mut self) {
+        let (min, max) = self.members.values().fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(min, max), (position, half_size)| {
+                (min.min(*position - *half_size), max.max(*position + *half_size))
+            },
+        );
+        self.min = min;
+        self.max = max;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+}
 
 pub struct EnemyPlugin;
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_startup_system_to_stage(
-                StartupStage::PostStartup,
-                setup_enemies
-            )
+            .register_type::<Enemy>()
+            .register_type::<AlienType>()
+            .register_type::<FromEnemy>()
+            .add_event::<EnemyDestroyed>()
+            .init_resource::<FleetFire>()
             .add_system_set(
-                SystemSet::new()
-                    .with_system(manage_alien_movement_direction)
-                    .with_system(manage_alien_horizontal_movement)
-                    .with_system(manage_alien_vertical_movement)
-                    .with_system(alien_hit_obstacle)
+                SystemSet::on_enter(GameState::InGame)
+                    .with_system(setup_enemies)
             )
             .add_system_set(
-                SystemSet::new()
-                    .with_run_criteria(FixedTimestep::step(0.800))
-                    .with_system(alien_random_shoot)
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(
+                        manage_alien_movement_direction
+                            .label("formation_direction")
+                            .label("movement")
+                            .after("player_shooting")
+                    )
+                    .with_system(
+                        manage_alien_horizontal_movement
+                            .label("formation_horizontal")
+                            .label("movement")
+                            .after("formation_direction")
+                    )
+                    .with_system(
+                        manage_alien_vertical_movement
+                            .label("formation_vertical")
+                            .label("movement")
+                            .after("formation_horizontal")
+                    )
+                    .with_system(alien_hit_obstacle.label("collisions").after("movement"))
+                    .with_system(manage_wave_progress.label("wave_progress").after("player_lives"))
+                    .with_system(trigger_death_effects.after("collisions"))
+                    .with_system(fleet_fire.label("fleet_fire").after("wave_progress"))
             )
+            // Removals are only visible after the stage that despawned them
+            .add_system_to_stage(CoreStage::PostUpdate, track_formation_bounds)
             ;
     }
 }
 
 fn setup_enemies(
     mut commands: Commands,
-    sprite_infos: Res<SpriteInfos>
+    mut game: ResMut<Game>,
+    difficulty: Res<Difficulty>,
+    sprite_infos: Res<SpriteInfos>,
+    pending_load: Res<PendingLoad>,
+    board_scene: Res<BoardScene>,
 ) {
     // setup resources
-    commands.insert_resource(AlienState {
-        movement_direction: -1.,
-        movement_speed: MovementSpeed { value: 30. },
-        // movement_speed: MovementSpeed { value: 100. },
-        move_down: false,
-        available_to_shoot: 2,
-    });
+    game.wave = 1;
+    commands.insert_resource(AlienState::for_wave(game.wave, &difficulty));
+    commands.insert_resource(FleetFire::default());
+
+    // The saved or scene fleet replaces it
+    if pending_load.0.is_none() && !board_scene.is_set() {
+        spawn_fleet(&mut commands, &sprite_infos);
+    }
+}
 
+pub fn spawn_fleet(commands: &mut Commands, sprite_infos: &SpriteInfos) {
     let alien_rows = 6;
     let alien_cols = 8;
-    let x_distance: f32 = 60.;
+    let x_distance: f32 = FLEET_COLUMN_WIDTH;
     let y_distance: f32 = 48.;
     let x_offset: f32 = -210.;
     let y_offset: f32 = -50.;
 
+    let mut aliens = Vec::new();
     for (row_idx, row) in (0..alien_rows).rev().enumerate() {
         for (col_idx, _) in (0..alien_cols).enumerate() {
             let x = col_idx as f32 * x_distance + x_offset;
@@ -154,28 +399,80 @@ fn setup_enemies(
 
             let alien_type = match row {
                 0 => AlienType::YELLOW,
-                a if a >= 1 && a <= 2 => AlienType::GREEN,
+                1..=2 => AlienType::GREEN,
                 _ => AlienType::RED,
             };
         
-            commands.spawn_bundle(AlienBundle::new(
-                x, y, alien_type, &sprite_infos)
-            );
+            aliens.push((Vec2::new(x, y), alien_type));
         }
     }
+    spawn_formation(commands, Vec3::ZERO, &aliens, sprite_infos);
+}
+
+/// Spawns `aliens` as children of a new formation at `origin`, their
+/// positions being relative to it. Returns the formation and the aliens
+/// in the order given.
+pub fn spawn_formation(
+    commands: &mut Commands,
+    origin: Vec3,
+    aliens: &[(Vec2, AlienType)],
+    sprite_infos: &SpriteInfos,
+) -> (Entity, Vec<Entity>) {
+    let mut formation = Formation::default();
+    let children = aliens
+        .iter()
+        .map(|(position, alien_type)| {
+            let entity = spawn_alien(commands, origin, position.x, position.y, *alien_type, sprite_infos);
+            formation.add(entity, *position, alien_type.sprite(sprite_infos).1 / 2.);
+            entity
+        })
+        .collect::<Vec<_>>();
+
+    let formation_en = commands
+        .spawn()
+        .insert(formation)
+        .insert(Transform::from_translation(origin))
+        .insert(GlobalTransform::from_translation(origin))
+        .push_children(&children)
+        .id();
+    (formation_en, children)
 }
 
-fn setup_top_alien(
+/// Sends in the next wave once the fleet has been wiped out
+#[allow(clippy::too_many_arguments)]
+fn manage_wave_progress(
     mut commands: Commands,
-    sprite_infos: Res<SpriteInfos>
+    mut game: ResMut<Game>,
+    mut alien_state: ResMut<AlienState>,
+    enemy_q: Query<(), With<Enemy>>,
+    difficulty: Res<Difficulty>,
+    win_size: Res<WinSize>,
+    sprite_infos: Res<SpriteInfos>,
+    pending_load: Res<PendingLoad>,
 ) {
-    
+    // An empty board is only waiting for the saved one
+    if enemy_q.iter().next().is_some() || pending_load.0.is_some() {
+        return;
+    }
+
+    game.wave += 1;
+    *alien_state = AlienState::for_wave(game.wave, &difficulty);
+    spawn_wave(&mut commands, game.wave, &win_size, &sprite_infos);
+}
+
+/// Either the usual fleet or, every few waves, a boss
+pub fn spawn_wave(commands: &mut Commands, wave: u32, win_size: &WinSize, sprite_infos: &SpriteInfos) {
+    if is_boss_wave(wave) {
+        spawn_boss(commands, wave, win_size, sprite_infos);
+    } else {
+        spawn_fleet(commands, sprite_infos);
+    }
 }
 
 pub fn alien_hit_obstacle (
     mut commands: Commands,
     obstacle_q: Query<(Entity, &RenderedAssetInfo, &Transform), With<Obstacle>>,
-    enemy_q: Query<(&RenderedAssetInfo, &Transform), With<Enemy>>,
+    enemy_q: Query<(&RenderedAssetInfo, &GlobalTransform), With<Enemy>>,
 ) {
     let mut entities_despawned: HashSet<Entity> = HashSet::new();
     for (ob_en, ob_rai, ob_tf) in obstacle_q.iter() {
@@ -187,66 +484,161 @@ pub fn alien_hit_obstacle (
                 en_rai.size
             );
 
-            if let Some(_) = collision {
-                if (entities_despawned.get(&ob_en)).is_none() {
-                    commands.entity(ob_en).despawn();
-                    entities_despawned.insert(ob_en);
-                }
+            if collision.is_some() && !entities_despawned.contains(&ob_en) {
+                commands.entity(ob_en).despawn();
+                entities_despawned.insert(ob_en);
             }
         }
     }
 }
 
-pub fn alien_random_shoot(
+/// Fires volleys of up to `available_to_shoot` shots, rotating through the
+/// shot kinds, while staying under the difficulty's cap of alien shots on
+/// screen
+#[allow(clippy::too_many_arguments)]
+fn fleet_fire(
     mut commands: Commands,
-    mut q: Query<(&mut WeaponState, &Transform), With<Enemy>>,
-    mut alien_state: ResMut<AlienState>,
+    mut fleet_fire: ResMut<FleetFire>,
+    q: Query<(&WeaponState, &GlobalTransform, &AlienType), With<Enemy>>,
+    shot_q: Query<(), (With<Projectile>, With<FromEnemy>)>,
+    player_q: Query<&Transform, (With<Player>, Without<Enemy>)>,
+    alien_state: Res<AlienState>,
+    difficulty: Res<Difficulty>,
+    game: Res<Game>,
     time: Res<Time>,
     sprite_infos: Res<SpriteInfos>,
     asset_scaling: Res<AssetScaling>,
+    mut game_rng: ResMut<GameRng>,
 ) {
-    // TODO: how to choose randomly more efficiently?
-    let vec_q = q
+    // Picked in board order rather than query order, so a loaded game
+    // fires from the same aliens
+    let mut aliens = q.iter().collect::<Vec<_>>();
+    aliens.sort_by(|(_, a, _), (_, b, _)| {
+        a.translation.x.total_cmp(&b.translation.x).then(a.translation.y.total_cmp(&b.translation.y))
+    });
+    if fleet_fire.wave != game.wave {
+        fleet_fire.wave = game.wave;
+        fleet_fire.fleet_size = aliens.len();
+        fleet_fire.next_shot = 0;
+    }
+
+    if !fleet_fire.reload.tick(time.delta()).finished() {
+        return;
+    }
+    let reload = fleet_fire.reload_secs(&difficulty, game.wave, aliens.len());
+    fleet_fire.reload = Timer::from_seconds(reload, false);
+
+    let room = difficulty.max_alien_shots.saturating_sub(shot_q.iter().count() as u32);
+    let rng = &mut game_rng.0;
+    let mut player_xs = player_q
         .iter()
+        .map(|tf| tf.translation.x)
         .collect::<Vec<_>>();
-    let q_rand = vec_q
-        .choose_multiple(&mut rand::thread_rng(), alien_state.available_to_shoot.try_into().unwrap());
-
-    for (weapon_state, tf) in q_rand.into_iter() {
-        if weapon_state.ready {
-            let pos = tf.translation.truncate();
-            let asset_size = 
-                asset_scaling.enemy_projectile.truncate() * sprite_infos.alien_laser.1;
-            let asset_info = RenderedAssetInfo::new(asset_size);
-            
+    player_xs.sort_by(f32::total_cmp);
+
+    for _ in 0..alien_state.available_to_shoot.min(room) {
+        let kind = match fleet_fire.take_shot(|kind| {
+            aliens.iter().any(|(_, _, alien_type)| alien_type.archetype().shot == kind)
+        }) {
+            Some(kind) => kind,
+            None => return,
+        };
+        let shooters = aliens
+            .iter()
+            .filter(|(_, _, alien_type)| alien_type.archetype().shot == kind)
+            .collect::<Vec<_>>();
+        let (weapon_state, tf, alien_type) = match shooters.choose_weighted(
+            rng,
+            |(_, _, alien_type)| alien_type.archetype().fire_weight,
+        ) {
+            Ok(shooter) => **shooter,
+            Err(_) => continue,
+        };
+
+        let profile = alien_type.archetype().shot.profile();
+        let origin = match player_xs.choose(rng) {
+            Some(&player_x) if profile.tracks_player => {
+                column_above(player_x, aliens.iter().map(|(_, tf, _)| tf.translation))
+                    .unwrap_or(tf.translation)
+            }
+            _ => tf.translation,
+        };
+        let scale = asset_scaling.enemy_projectile * profile.scale.extend(1.);
+
+        let projectiles = fire_weapon(
+            &mut commands,
+            &weapon_state.weapon,
+            origin,
+            -1.,
+            &sprite_infos.alien_laser,
+            scale,
+            0.,
+        );
+        for projectile in projectiles {
             commands
-                .spawn()
-                .insert_bundle(SpriteBundle {
-                    sprite: Sprite {
-                        custom_size: Some(asset_size),
-                        ..Default::default()
-                    },
-                    texture: sprite_infos.alien_laser.0.clone(),
-                    transform: Transform {
-                        // quaternion rotation using radians
-                        rotation: Quat::from_rotation_z(PI),
-                        translation: Vec3::new(pos.x, pos.y, 0.),
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                })
-                .insert(asset_info)
-                .insert(Projectile::default())
-                .insert(Velocity::new(0., -weapon_state.projectile_speed))
+                .entity(projectile)
                 .insert(FromEnemy)
-                ;
+                .insert(Motion::new(profile.pattern))
+                .insert(Sprite {
+                    color: profile.tint,
+                    custom_size: Some(scale.truncate() * sprite_infos.alien_laser.1),
+                    ..Default::default()
+                });
+        }
+    }
+}
+
+fn trigger_death_effects(
+    mut commands: Commands,
+    mut events: EventReader<EnemyDestroyed>,
+    sprite_infos: Res<SpriteInfos>,
+    asset_scaling: Res<AssetScaling>,
+    settings: Res<Settings>,
+) {
+    for event in events.iter() {
+        let death_effect = match event.alien_type {
+            Some(alien_type) => alien_type.archetype().death_effect,
+            None => DeathEffect::Explosion,
+        };
+
+        if settings.explosions {
+            spawn_explosion(&mut commands, event.position);
+        }
+        if let DeathEffect::Burst { shots } = death_effect {
+            let mut weapon = Weapon::new(WeaponKind::Spread);
+            weapon.projectile_count = shots;
+            weapon.spread = 1.2;
+            weapon.projectile_speed = 150.;
+
+            let projectiles = fire_weapon(
+                &mut commands,
+                &weapon,
+                event.position,
+                -1.,
+                &sprite_infos.alien_laser,
+                asset_scaling.enemy_projectile,
+                0.,
+            );
+            for projectile in projectiles {
+                commands.entity(projectile).insert(FromEnemy);
+            }
         }
     }
 }
 
+/// Lowest alien of the column closest to `x`, where the rolling shot drops from
+fn column_above(x: f32, aliens: impl Iterator<Item = Vec3>) -> Option<Vec3> {
+    let column = |pos: &Vec3| ((pos.x - x).abs() / FLEET_COLUMN_WIDTH).round();
+    aliens.min_by(|a, b| {
+        column(a)
+            .total_cmp(&column(b))
+            .then(a.y.total_cmp(&b.y))
+    })
+}
+
 fn manage_alien_horizontal_movement(
-    mut q: Query<&mut Transform, With<Enemy>>,
-    mut alien_state: ResMut<AlienState>
+    mut q: Query<&mut Transform, With<Formation>>,
+    alien_state: Res<AlienState>
 ) {
     for mut tf in q.iter_mut() {
         tf.translation.x += 
@@ -258,34 +650,263 @@ fn manage_alien_horizontal_movement(
 
 fn manage_alien_vertical_movement(
     mut commands: Commands,
-    mut q: Query<(Entity, &mut Transform), With<Enemy>>,
+    mut q: Query<(Entity, &Formation, &mut Transform)>,
     mut alien_state: ResMut<AlienState>,
     win_size: Res<WinSize>,
 ) {
-    // let mut entities_despawned: HashSet<Entity> = HashSet::new();
     if alien_state.move_down {
-        for (en, mut tf) in q.iter_mut() {
-            tf.translation.y += -10.;
+        for (formation_en, formation, mut tf) in q.iter_mut() {
+            tf.translation.y -= alien_state.descent_step;
 
-            if tf.translation.y.abs() > win_size.h / 2. {
-                commands.entity(en).despawn();
+            // Aliens that made it past the bottom are gone
+            let gone = formation.members.iter()
+                .filter(|(_, (position, _))| (tf.translation.y + position.y).abs() > win_size.h / 2.)
+                .map(|(en, _)| *en)
+                .collect::<Vec<_>>();
+            if !gone.is_empty() {
+                commands.entity(formation_en).remove_children(&gone);
+                for en in gone {
+                    commands.entity(en).despawn_recursive();
+                }
             }
         }
         alien_state.move_down = false;
     }
 }
 
+/// Turns the fleet around when it reaches an edge. Formations are pulled
+/// back inside the screen and only an edge the fleet is heading toward
+/// counts, so each edge reverses the fleet and drops it exactly once.
 fn manage_alien_movement_direction(
-    mut q: Query<(&mut Transform, &RenderedAssetInfo), With<Enemy>>,
+    mut q: Query<(&Formation, &mut Transform)>,
     mut alien_state: ResMut<AlienState>,
     win_size: Res<WinSize>,
 ) {
-    for (tf, info) in q.iter_mut() {
-        let curr_x = tf.translation.x;
-        if curr_x.abs() >= (win_size.w / 2.) - (info.size.x / 2.) {
-            alien_state.movement_direction *= -1.;
-            alien_state.move_down = true;
-            break;
+    let mut hit_edge = false;
+    for (formation, mut tf) in q.iter_mut() {
+        if formation.is_empty() {
+            continue;
+        }
+
+        let left = tf.translation.x + formation.min.x;
+        let right = tf.translation.x + formation.max.x;
+        if let Some(correction) = edge_correction(left, right, alien_state.movement_direction, win_size.w / 2.) {
+            tf.translation.x += correction;
+            hit_edge = true;
+        }
+    }
+
+    if hit_edge {
+        alien_state.movement_direction *= -1.;
+        alien_state.move_down = true;
+    }
+}
+
+/// How far to move a formation spanning `left..right` back inside the
+/// screen, if it went past the edge it is moving toward
+fn edge_correction(left: f32, right: f32, direction: f32, half_width: f32) -> Option<f32> {
+    if direction < 0. && left <= -half_width {
+        Some(-half_width - left)
+    } else if direction > 0. && right >= half_width {
+        Some(half_width - right)
+    } else {
+        None
+    }
+}
+
+/// Drops destroyed aliens from their formation and removes formations
+/// left empty
+fn track_formation_bounds(
+    mut commands: Commands,
+    removed: RemovedComponents<Enemy>,
+    mut q: Query<(Entity, &mut Formation)>,
+) {
+    let removed = removed.iter().collect::<Vec<_>>();
+    if removed.is_empty() {
+        return;
+    }
+
+    for (entity, mut formation) in q.iter_mut() {
+        for alien in removed.iter() {
+            if formation.members.contains_key(alien) {
+                formation.remove(*alien);
+            }
+        }
+        if formation.is_empty() {
+            commands.entity(entity).despawn();
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::schedule::{Stage, SystemStage};
+
+    use super::*;
+
+    const WIN_SIZE: f32 = 600.;
+    /// Half the size of an alien sprite
+    const ALIEN_HALF_SIZE: [f32; 2] = [12., 8.];
+    const FRAMES: usize = 600;
+    /// Every this many frames the fleet moves several steps before the edge
+    /// check, as it would after a long frame
+    const SPIKE_EVERY: usize = 37;
+    const SPIKE_STEPS: usize = 8;
+
+    struct Fleet {
+        world: World,
+        formation: Entity,
+        aliens: Vec<(usize, Entity)>,
+        moving: SystemStage,
+        turning: SystemStage,
+        descending: SystemStage,
+    }
+    impl Fleet {
+        /// Three rows in each of `columns`, laid out like `spawn_fleet`
+        fn new(columns: &[usize], speed: f32) -> Self {
+            let mut world = World::new();
+            let mut alien_state = AlienState::for_wave(1, &Difficulty::default());
+            alien_state.movement_speed.value = speed;
+            world.insert_resource(alien_state);
+            world.insert_resource(WinSize { w: WIN_SIZE, h: WIN_SIZE });
+
+            let mut formation = Formation::default();
+            let mut aliens = Vec::new();
+            for &column in columns {
+                for row in 0..3 {
+                    let entity = world.spawn().insert(Enemy).id();
+                    let position = Vec2::new(column as f32 * FLEET_COLUMN_WIDTH - 210., row as f32 * 48.);
+                    formation.add(entity, position, Vec2::from(ALIEN_HALF_SIZE));
+                    aliens.push((column, entity));
+                }
+            }
+            let formation = world
+                .spawn()
+                .insert(formation)
+                .insert(Transform::default())
+                .id();
+
+            Self {
+                world,
+                formation,
+                aliens,
+                moving: SystemStage::single_threaded().with_system(manage_alien_horizontal_movement),
+                turning: SystemStage::single_threaded().with_system(manage_alien_movement_direction),
+                descending: SystemStage::single_threaded().with_system(manage_alien_vertical_movement),
+            }
+        }
+
+        fn direction(&self) -> f32 {
+            self.world.get_resource::<AlienState>().unwrap().movement_direction
+        }
+
+        fn descent_step(&self) -> f32 {
+            self.world.get_resource::<AlienState>().unwrap().descent_step
+        }
+
+        /// Left and right edges of the fleet, and its height
+        fn bounds(&self) -> (f32, f32, f32) {
+            let tf = self.world.get::<Transform>(self.formation).unwrap();
+            let formation = self.world.get::<Formation>(self.formation).unwrap();
+            (tf.translation.x + formation.min.x, tf.translation.x + formation.max.x, tf.translation.y)
+        }
+
+        /// Shoots down every alien of `column`
+        fn destroy_column(&mut self, column: usize) {
+            let (gone, kept) = self.aliens.drain(..).partition(|(col, _)| *col == column);
+            self.aliens = kept;
+            for (_, entity) in gone {
+                self.world.get_mut::<Formation>(self.formation).unwrap().remove(entity);
+                self.world.despawn(entity);
+            }
+        }
+
+        /// Runs one frame and checks the fleet against the screen edges.
+        /// Returns whether the fleet turned around.
+        fn step(&mut self, moves: usize) -> bool {
+            let direction = self.direction();
+            let (_, _, y) = self.bounds();
+
+            for _ in 0..moves {
+                self.moving.run(&mut self.world);
+            }
+            self.turning.run(&mut self.world);
+            self.descending.run(&mut self.world);
+
+            let (left, right, new_y) = self.bounds();
+            let half_width = WIN_SIZE / 2.;
+            assert!(left >= -half_width - 1e-3, "fleet left the screen on the left at {}", left);
+            assert!(right <= half_width + 1e-3, "fleet left the screen on the right at {}", right);
+
+            let turned = self.direction() != direction;
+            let expected_drop = if turned { self.descent_step() } else { 0. };
+            assert!(
+                (y - new_y - expected_drop).abs() < 1e-3,
+                "fleet dropped {} on a frame it {} turn",
+                y - new_y,
+                if turned { "did" } else { "didn't" },
+            );
+            if turned {
+                // Turned at the edge it was heading toward, and only that one
+                let edge = if direction < 0. { left + half_width } else { right - half_width };
+                assert!(edge.abs() < 1e-3, "fleet turned {} away from the edge", edge);
+            }
+            turned
+        }
+
+        /// Runs `FRAMES` frames with the occasional spike, shooting down the
+        /// columns in `losses` along the way. Returns the number of turns.
+        fn run(&mut self, losses: &[(usize, usize)]) -> usize {
+            let mut turns = 0;
+            for frame in 1..=FRAMES {
+                for &(_, column) in losses.iter().filter(|(at, _)| *at == frame) {
+                    self.destroy_column(column);
+                }
+                let moves = if frame % SPIKE_EVERY == 0 { SPIKE_STEPS } else { 1 };
+                if self.step(moves) {
+                    turns += 1;
+                }
+            }
+            turns
+        }
+    }
+
+    #[test]
+    fn full_fleet_stays_on_screen() {
+        let mut fleet = Fleet::new(&[0, 1, 2, 3, 4, 5, 6, 7], 240.);
+        assert!(fleet.run(&[]) > 10);
+    }
+
+    #[test]
+    fn lone_columns_stay_on_screen() {
+        for column in [0, 3, 7] {
+            let mut fleet = Fleet::new(&[column], 300.);
+            assert!(fleet.run(&[]) > 3);
+        }
+    }
+
+    #[test]
+    fn shrinking_fleet_stays_on_screen() {
+        let mut fleet = Fleet::new(&[0, 1, 2, 3, 4, 5, 6, 7], 240.);
+        let losses = [(50, 0), (120, 7), (200, 1), (260, 6), (400, 2), (410, 5), (500, 3)];
+        assert!(fleet.run(&losses) > 5);
+    }
+
+    #[test]
+    fn slow_fleet_turns_once_per_edge() {
+        // Crawling along the edge must not turn the fleet every frame
+        let mut fleet = Fleet::new(&[2, 3, 4], 60.);
+        let turns = fleet.run(&[]);
+        assert!(turns >= 1);
+    }
+
+    #[test]
+    fn edge_check_twice_turns_once() {
+        let mut fleet = Fleet::new(&[0, 1, 2, 3, 4, 5, 6, 7], 300.);
+        while !fleet.step(1) {}
+
+        let direction = fleet.direction();
+        fleet.turning.run(&mut fleet.world);
+        assert_eq!(fleet.direction(), direction);
+    }
+}
@@ -1,6 +1,8 @@
-use bevy::{prelude::*, ecs::bundle, utils::HashSet};
+use bevy::prelude::*;
 
-use crate::{shared::{Health, RenderedAssetInfo}, WinSize};
+use crate::{shared::{Health, RenderedAssetInfo}, savegame::PendingLoad, GameState, WinSize};
+
+use super::BoardScene;
 
 const DEFAULT_OBSTACLE_AMOUNT: u32 = 4;
 const OBSTACLE_BLOCK_SIZE: f32 = 6.;
@@ -14,45 +16,19 @@ const OBSTACLE_SHAPE: [&str; 7] = [
     "xx       xx" 
 ];
 
-#[derive(Bundle)]
-struct BlockBundle {
-    #[bundle]
-    _sb: SpriteBundle,
-    _o: Obstacle,
-    _hp: Health,
-    _rai: RenderedAssetInfo,
-}
-impl BlockBundle {
-    fn new(x: f32, y: f32, color: Color) -> Self {
-        let obs_size = Vec2::new(OBSTACLE_BLOCK_SIZE, OBSTACLE_BLOCK_SIZE) ;
-        Self {
-            _sb: SpriteBundle {
-                sprite: Sprite {
-                    color: color,
-                    custom_size: Some(obs_size.clone()),
-                    ..Default::default()
-                },
-                transform: Transform {
-                    translation: Vec3::new(x, y, 5.), 
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            _o: Obstacle,
-            _hp: Health::default(),
-            _rai: RenderedAssetInfo::new(obs_size.clone())
-        }
-    }
-}
-
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct Obstacle;
 
 pub struct ObstaclesPlugin;
 impl Plugin for ObstaclesPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_startup_system(setup_obstacles)
+            .register_type::<Obstacle>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame)
+                    .with_system(setup_obstacles)
+            )
         ;
     }
 }
@@ -60,7 +36,16 @@ impl Plugin for ObstaclesPlugin {
 fn setup_obstacles(
     mut commands: Commands,
     win_size: Res<WinSize>,
+    pending_load: Res<PendingLoad>,
+    board_scene: Res<BoardScene>,
 ) {
+    if pending_load.0.is_some() || board_scene.is_set() {
+        return;
+    }
+    spawn_obstacles(&mut commands, &win_size);
+}
+
+pub fn spawn_obstacles(commands: &mut Commands, win_size: &WinSize) {
     let x_start = (-win_size.w / 2.) + (win_size.w / 15.);
     let y_start = -(win_size.h/2.) + 100.;
     let num_obstacles = DEFAULT_OBSTACLE_AMOUNT;
@@ -73,7 +58,7 @@ fn setup_obstacles(
     println!("{:?}", obstacle_x_offsets);
     for offset in obstacle_x_offsets {
         create_obstacle(
-            &mut commands, 
+            commands, 
             Vec2::new(x_start, y_start), 
             offset
         );
@@ -87,11 +72,30 @@ fn create_obstacle(commands: &mut Commands, start_position: Vec2, offset_x: f32)
                 let x = start_position.x + (col_idx as f32 * (OBSTACLE_BLOCK_SIZE)) + offset_x;
                 let y = start_position.y + (row_idx as f32 * (OBSTACLE_BLOCK_SIZE));
 
-                let color = Color::hex("F14F50").unwrap();
-                commands
-                    .spawn_bundle(BlockBundle::new(x, y, color));
-                    // .spawn_bundle(BlockBundle::new(x, y, Color::ORANGE_RED));
+                spawn_block(commands, x, y);
             }
         }
     }
+}
+
+pub fn spawn_block(commands: &mut Commands, x: f32, y: f32) -> Entity {
+    let color = Color::hex("F14F50").unwrap();
+    let obs_size = Vec2::new(OBSTACLE_BLOCK_SIZE, OBSTACLE_BLOCK_SIZE);
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(obs_size),
+                ..Default::default()
+            },
+            transform: Transform {
+                translation: Vec3::new(x, y, 5.),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(Obstacle)
+        .insert(Health::default())
+        .insert(RenderedAssetInfo::new(obs_size))
+        .id()
 }
\ No newline at end of file
@@ -1,53 +1,240 @@
 use bevy::{prelude::*, sprite::collide_aabb::collide};
+use serde::{Deserialize, Serialize};
 
-use crate::{WinSize, SpriteInfos, shared::{Health, WeaponState, MovementSpeed, Projectile, Velocity, RenderedAssetInfo}, GAME_TIME_STEP, AssetScaling};
+use crate::{WinSize, SpriteInfos, AudioInfos, GameMode, GameState, controls::{Action, ActionState, SchemeActions}, savegame::PendingLoad, settings::Settings, shared::{Health, WeaponState, RenderedAssetInfo, Velocity, fire_weapon}, GAME_TIME_STEP, AssetScaling};
 
-use super::Enemy;
+use super::{Ability, ActiveModifiers, BoardScene, BoardSnapshot, Enemy, Ship, ShipDef, TurnChanged};
 
-#[derive(Component)]
-pub struct FromPlayer;
+const PLAYER_LIVES: u32 = 3;
+const PLAYER_RESPAWN_DELAY: f32 = 2.;
+const PLAYER_SPAWN_SPACING: f32 = 200.;
+const PLAYER_TINTS: [Color; 2] = [
+    Color::rgb(0.6, 1., 0.6),
+    Color::rgb(0.6, 0.8, 1.),
+];
 
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct FromPlayer {
+    /// Index into `Players::slots` of the shooter
+    pub player: usize,
+}
+
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct Player;
 
-#[derive(Component)]
+/// Sent when a player loses a life
+pub struct PlayerDied;
+
+/// Sent for every projectile a player fires
+pub struct PlayerFired;
+
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct PlayerState {
-    name: String
+    pub index: usize,
+}
+
+/// How a ship handles
+#[derive(Component, Reflect, Clone, Debug, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct MovementModel {
+    /// Speed gained per second while a direction is held
+    pub acceleration: f32,
+    /// Speed lost per second on an axis with no input
+    pub friction: f32,
+    pub max_speed: f32,
+    /// How far above its starting row the ship may fly, 0 keeps it on the row
+    pub vertical_range: f32,
+}
+impl Default for MovementModel {
+    fn default() -> Self {
+        Self {
+            acceleration: 1800.,
+            friction: 1400.,
+            max_speed: 250.,
+            vertical_range: 0.,
+        }
+    }
+}
+
+/// Everything about a player that outlives their ship
+pub struct PlayerSlot {
+    pub name: String,
+    pub lives: u32,
+    pub score: u32,
+    pub tint: Color,
+    /// Control scheme of this player, `None` accepts every scheme
+    pub controls: Option<usize>,
+    /// Board put aside while the other player takes their turn
+    pub board: Option<BoardSnapshot>,
+    /// Ship chosen on the ship selection screen, the default ship if none
+    pub ship: Option<Ship>,
+    alive: bool,
+    respawn_timer: Option<Timer>,
+}
+impl PlayerSlot {
+    fn new(index: usize, controls: Option<usize>) -> Self {
+        Self {
+            name: format!("Player {}", index + 1),
+            lives: PLAYER_LIVES,
+            score: 0,
+            tint: PLAYER_TINTS[index % PLAYER_TINTS.len()],
+            controls,
+            board: None,
+            ship: None,
+            alive: false,
+            respawn_timer: None,
+        }
+    }
+
+    /// Whether the ship is on the board, or was lost this frame
+    pub fn is_alive(&self) -> bool {
+        self.alive
+    }
+
+    /// Time until the ship comes back, while waiting to respawn
+    pub fn respawn_timer(&self) -> Option<&Timer> {
+        self.respawn_timer.as_ref()
+    }
+
+    /// Puts back the state of a saved slot, its ship being spawned separately
+    pub fn restore(&mut self, alive: bool, respawn_timer: Option<Timer>) {
+        self.alive = alive;
+        self.respawn_timer = respawn_timer;
+    }
+}
+
+pub struct Players {
+    pub mode: GameMode,
+    pub slots: Vec<PlayerSlot>,
+    /// Player whose board is in play when alternating
+    pub turn: usize,
+}
+impl Default for Players {
+    fn default() -> Self {
+        Self::new(GameMode::SinglePlayer)
+    }
+}
+impl Players {
+    pub fn new(mode: GameMode) -> Self {
+        let slots = match mode {
+            GameMode::SinglePlayer => vec![PlayerSlot::new(0, None)],
+            GameMode::Coop => vec![
+                PlayerSlot::new(0, Some(0)),
+                PlayerSlot::new(1, Some(1)),
+            ],
+            // Like the cabinet, whoever is up uses any controls
+            GameMode::Alternating => vec![
+                PlayerSlot::new(0, None),
+                PlayerSlot::new(1, None),
+            ],
+        };
+        Self { mode, slots, turn: 0 }
+    }
+
+    /// Whether this player's ship belongs on the current board
+    pub fn is_playing(&self, index: usize) -> bool {
+        self.mode != GameMode::Alternating || index == self.turn
+    }
+
+    /// Who plays after `index` loses a ship: the other player when alternating
+    /// and they have lives left, otherwise the same player if they can continue.
+    fn next_turn(&self, index: usize) -> Option<usize> {
+        if self.mode == GameMode::Alternating {
+            let other = (index + 1) % self.slots.len();
+            if self.slots[other].lives > 0 {
+                return Some(other);
+            }
+        }
+        if self.slots[index].lives > 0 { Some(index) } else { None }
+    }
+
+    pub fn spawn_x(&self, index: usize) -> f32 {
+        if self.mode == GameMode::Alternating {
+            return 0.;
+        }
+        let count = self.slots.len();
+        (index as f32 - (count - 1) as f32 / 2.) * PLAYER_SPAWN_SPACING
+    }
 }
 
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_startup_stage(
-                "player_setup", 
-                SystemStage::single(player_spawn.after("main_setup"))
+            .register_type::<Player>()
+            .register_type::<PlayerState>()
+            .register_type::<FromPlayer>()
+            .register_type::<MovementModel>()
+            .init_resource::<Players>()
+            .add_event::<PlayerDied>()
+            .add_event::<PlayerFired>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame)
+                    .with_system(player_spawn)
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(player_movement.label("player_movement").after("weapons_state"))
+                    .with_system(player_shooting.label("player_shooting").after("player_movement"))
+                    .with_system(player_hit_enemy.label("collisions").after("movement"))
+                    .with_system(manage_player_lives.label("player_lives").after("collisions"))
             )
-            .add_system(player_movement)
-            .add_system(player_shooting)
-            .add_system(player_hit_enemy)
         ;
     }
 }
 
 fn player_spawn (
     mut commands: Commands,
+    mut players: ResMut<Players>,
     win_size: Res<WinSize>,
     sprite_infos: Res<SpriteInfos>,
+    pending_load: Res<PendingLoad>,
+    board_scene: Res<BoardScene>,
 ) {
-    let asset = sprite_infos.player.clone();
+    if pending_load.0.is_some() || board_scene.is_set() {
+        return;
+    }
+    players.turn = 0;
+    for index in 0..players.slots.len() {
+        if players.is_playing(index) {
+            let x = players.spawn_x(index);
+            let position = Vec2::new(x, player_row(&win_size));
+            spawn_player(&mut commands, &sprite_infos, index, position, &mut players.slots[index]);
+        }
+    }
+}
+
+/// Spawns the ship of `slot` at `position`, returning it
+pub fn spawn_player(
+    commands: &mut Commands,
+    sprite_infos: &SpriteInfos,
+    index: usize,
+    position: Vec2,
+    slot: &mut PlayerSlot,
+) -> Entity {
+    let ship = slot.ship.clone().unwrap_or_else(|| Ship {
+        def: ShipDef::default(),
+        sprite: sprite_infos.player.clone(),
+    });
+    let asset = ship.sprite;
     let asset_size = Vec2::new(
         1. * asset.1.x,
         1. * asset.1.y,
     );
     let asset_info = RenderedAssetInfo::new(asset_size);
 
-    let bottom = -win_size.h / 2.;
-    commands
+    let entity = commands
         .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: slot.tint,
+                ..Default::default()
+            },
             texture: asset.0,
             transform: Transform {
-                translation: Vec3::new(0., bottom + 75. / 3. + 5., 10.),
+                translation: position.extend(10.),
                 scale: Vec3::new(1., 1., 10.),
                 ..Default::default()
             },
@@ -55,46 +242,132 @@ fn player_spawn (
         })
         .insert(Player)
         .insert(asset_info)
-        .insert(Health::default())
-        .insert(MovementSpeed { value: 250. })
-        .insert(PlayerState { name: "Player 1".to_string() })
-        .insert(WeaponState::fast_normal_weapon())
-    ;
+        .insert(Health::from(ship.def.hp))
+        .insert(ship.def.movement)
+        .insert(Velocity::new(0., 0.))
+        .insert(PlayerState { index })
+        .insert(WeaponState::new(ship.def.weapon))
+        .insert(ActiveModifiers::default())
+        .insert(Ability::new(ship.def.ability))
+        .id();
+    slot.alive = true;
+    entity
 }
 
-fn player_movement(
-    kb_in: Res<Input<KeyCode>>,
+/// Takes a life when a player's ship is gone, respawns it after a delay
+/// (handing the board over when alternating) and ends the game once
+/// every player is out of lives.
+#[allow(clippy::too_many_arguments)]
+fn manage_player_lives(
+    mut commands: Commands,
+    mut players: ResMut<Players>,
+    mut state: ResMut<State<GameState>>,
+    mut turn_events: EventWriter<TurnChanged>,
+    mut died_events: EventWriter<PlayerDied>,
+    q: Query<&PlayerState, With<Player>>,
+    time: Res<Time>,
     win_size: Res<WinSize>,
     sprite_infos: Res<SpriteInfos>,
-    mut q: Query<(&MovementSpeed, &mut Transform), With<Player>>,
 ) {
-    if let Ok((mov_spd, mut tf)) = q.get_single_mut() {
-        // TODO: QUERY WILL TRY TO MATCH ALL OF DESIRED
-        // SO WILL NOT WORK IF YOUR DESIRED DOES NOT IMPLEMENT BOTH COMPONENTS
-        let player_dimensions = sprite_infos.player.1;
-        let player_sprite_x = player_dimensions.x;
-        let target_bounds_x = win_size.w/2. - player_sprite_x/2.;
-        if kb_in.pressed(KeyCode::Left) || kb_in.pressed(KeyCode::A) {
-            let desired_x = tf.translation.x + (-1. * mov_spd.value * GAME_TIME_STEP);
-            if desired_x > -target_bounds_x {
-                tf.translation.x = desired_x
-            }
-        } else if kb_in.pressed(KeyCode::Right) || kb_in.pressed(KeyCode::D) {
-            let desired_x = tf.translation.x + (1. * mov_spd.value * GAME_TIME_STEP);
-            if desired_x < target_bounds_x {
-                tf.translation.x = desired_x
+    for index in 0..players.slots.len() {
+        let on_board = q.iter().any(|player| player.index == index);
+        if players.slots[index].alive && !on_board {
+            let slot = &mut players.slots[index];
+            slot.alive = false;
+            slot.lives = slot.lives.saturating_sub(1);
+            died_events.send(PlayerDied);
+
+            if let Some(next) = players.next_turn(index) {
+                if next != index {
+                    players.turn = next;
+                    turn_events.send(TurnChanged { from: index, to: next });
+                }
+                players.slots[next].respawn_timer =
+                    Some(Timer::from_seconds(PLAYER_RESPAWN_DELAY, false));
             }
-        };
+        }
+
+        let respawn = players.slots[index]
+            .respawn_timer
+            .as_mut()
+            .map(|timer| timer.tick(time.delta()).finished())
+            .unwrap_or(false);
+        if respawn {
+            let x = players.spawn_x(index);
+            let slot = &mut players.slots[index];
+            slot.respawn_timer = None;
+            let position = Vec2::new(x, player_row(&win_size));
+            spawn_player(&mut commands, &sprite_infos, index, position, slot);
+        }
+    }
+
+    if players.slots.iter().all(|slot| slot.lives == 0) {
+        // Retried next frame if another transition is already queued
+        let _ = state.set(GameState::GameOver);
+    }
+}
+
+/// Height ships spawn at and can't go below
+pub fn player_row(win_size: &WinSize) -> f32 {
+    -win_size.h / 2. + 75. / 3. + 5.
+}
+
+#[allow(clippy::type_complexity)]
+fn player_movement(
+    actions: Res<ActionState>,
+    scheme_actions: Res<SchemeActions>,
+    players: Res<Players>,
+    win_size: Res<WinSize>,
+    mut q: Query<(&PlayerState, &MovementModel, Option<&Ability>, &RenderedAssetInfo, &mut Velocity, &mut Transform), With<Player>>,
+) {
+    for (player, model, ability, rai, mut vel, mut tf) in q.iter_mut() {
+        let player_actions = scheme_actions.get(players.slots[player.index].controls, &actions);
+
+        // Opposing directions held together cancel out
+        let input = Vec2::new(
+            player_actions.value(Action::MoveRight) - player_actions.value(Action::MoveLeft),
+            player_actions.value(Action::MoveUp) - player_actions.value(Action::MoveDown),
+        );
+
+        let boost = ability.map(|ability| ability.speed_factor()).unwrap_or(1.);
+        vel.value += input * model.acceleration * boost * GAME_TIME_STEP;
+        let friction = model.friction * GAME_TIME_STEP;
+        if input.x == 0. {
+            vel.value.x = vel.value.x.signum() * (vel.value.x.abs() - friction).max(0.);
+        }
+        if input.y == 0. {
+            vel.value.y = vel.value.y.signum() * (vel.value.y.abs() - friction).max(0.);
+        }
+        vel.value = vel.value.clamp_length_max(model.max_speed * boost);
+
+        let player_sprite_x = rai.size.x;
+        let target_bounds_x = win_size.w/2. - player_sprite_x/2.;
+        let row = player_row(&win_size);
+
+        let desired = tf.translation.truncate() + vel.value * GAME_TIME_STEP;
+        let clamped = Vec2::new(
+            desired.x.clamp(-target_bounds_x, target_bounds_x),
+            desired.y.clamp(row, row + model.vertical_range),
+        );
+        // Running into a bound stops the ship on that axis
+        if clamped.x != desired.x {
+            vel.value.x = 0.;
+        }
+        if clamped.y != desired.y {
+            vel.value.y = 0.;
+        }
+        tf.translation.x = clamped.x;
+        tf.translation.y = clamped.y;
     }
 }
 
 fn player_hit_enemy(
     mut commands: Commands,
     player_q: Query<(Entity, &Transform, &RenderedAssetInfo), With<Player>>,
-    enemy_q: Query<(Entity, &Transform, &RenderedAssetInfo), With<Enemy>>,
+    enemy_q: Query<(&GlobalTransform, &RenderedAssetInfo), With<Enemy>>,
 ) {
-    if let Ok((p_en, p_tf, p_rai)) = player_q.get_single() {
-        for (en_en, en_tf, en_rai) in enemy_q.iter() {
+    for (p_en, p_tf, p_rai) in player_q.iter() {
+        for (en_tf, en_rai) in enemy_q.iter() {
             let collision = collide (
                 p_tf.translation,
                 p_rai.size,
@@ -102,53 +375,73 @@ fn player_hit_enemy(
                 en_rai.size
             );
 
-            if let Some(_) = collision {
-                // TODO: ensure despawned already
+            if collision.is_some() {
                 commands.entity(p_en).despawn();
+                break;
             }
         }
     }
 }
 
 
+#[allow(clippy::too_many_arguments)]
 fn player_shooting(
     mut commands: Commands,
-    mut q: Query<(&Transform, &mut WeaponState), With<Player>>,
+    mut fired_events: EventWriter<PlayerFired>,
+    mut q: Query<(&PlayerState, &Transform, &mut WeaponState), With<Player>>,
     time: Res<Time>,
-    kb: Res<Input<KeyCode>>,
+    actions: Res<ActionState>,
+    scheme_actions: Res<SchemeActions>,
+    players: Res<Players>,
     sprite_infos: Res<SpriteInfos>,
+    audio_infos: Res<AudioInfos>,
     asset_scaling: Res<AssetScaling>,
+    audio: Res<Audio>,
+    settings: Res<Settings>,
 ) {
-    if let Ok((player_tf, mut weapon_state)) = q.get_single_mut() {
-        if weapon_state.ready && (kb.pressed(KeyCode::Space) || kb.pressed(KeyCode::Z)) {
-            let pos = player_tf.translation;
-            let asset_size = 
-                asset_scaling.enemy_projectile.truncate() * sprite_infos.player_laser.1;
-            let asset_info = RenderedAssetInfo::new(asset_size);
-
-            commands
-                .spawn()
-                .insert_bundle(SpriteBundle {
-                    sprite: Sprite {
-                        custom_size: Some(asset_size),
-                        ..Default::default()
-                    },
-                    texture: sprite_infos.player_laser.0.clone(),
-                    transform: Transform {
-                        translation: Vec3::new(pos.x, pos.y, 0.),
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                })
-                .insert(asset_info)
-                .insert(Projectile::default())
-                .insert(Velocity::new(0., weapon_state.projectile_speed))
-                // .insert(Velocity::new(0., 150.))
-                .insert(FromPlayer)
-            ;
+    for (player, player_tf, mut weapon_state) in q.iter_mut() {
+        let player_actions = scheme_actions.get(players.slots[player.index].controls, &actions);
+        let fire_held = player_actions.pressed(Action::Fire);
+
+        // Charged weapons build up while fire is held and shoot on release
+        let charge_time = weapon_state.weapon.charge_time;
+        let charge = if charge_time > 0. {
+            match (weapon_state.charge, fire_held && weapon_state.ready) {
+                (held, true) => {
+                    let held = held.unwrap_or(0.) + time.delta_seconds();
+                    weapon_state.charge = Some(held.min(charge_time));
+                    None
+                }
+                (Some(held), false) => Some(held / charge_time),
+                (None, false) => None,
+            }
+        } else if weapon_state.ready && fire_held {
+            Some(0.)
+        } else {
+            None
+        };
+
+        if let Some(charge) = charge {
+            let projectiles = fire_weapon(
+                &mut commands,
+                &weapon_state.weapon,
+                player_tf.translation,
+                1.,
+                &sprite_infos.player_laser,
+                asset_scaling.player_projectile,
+                charge,
+            );
+            for projectile in projectiles {
+                commands.entity(projectile).insert(FromPlayer { player: player.index });
+                fired_events.send(PlayerFired);
+            }
+
+            if settings.sound_on() {
+                audio.play(audio_infos.laser.clone());
+            }
 
             // Set weapon state
-            weapon_state.fired(time.seconds_since_startup());
+            weapon_state.fired();
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,276 @@
+use bevy::{prelude::*, sprite::collide_aabb::collide};
+use rand::{Rng, prelude::SliceRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::{GameRng, GameState, WinSize, GAME_TIME_STEP, shared::{Health, RenderedAssetInfo, WeaponState}};
+
+use super::{EnemyDestroyed, MovementModel, Obstacle, Player, PlayerState, Players, spawn_obstacles};
+
+/// Chance that a destroyed alien drops anything at all
+const DROP_CHANCE: f32 = 0.08;
+const DROP_TABLE: [(PowerUpKind, u32); 5] = [
+    (PowerUpKind::Shield, 3),
+    (PowerUpKind::RapidFire, 3),
+    (PowerUpKind::MultiShot, 2),
+    (PowerUpKind::ExtraLife, 1),
+    (PowerUpKind::BunkerRepair, 2),
+];
+const POWER_UP_SIZE: f32 = 12.;
+const POWER_UP_FALL_SPEED: f32 = 80.;
+const POWER_UP_DURATION: f32 = 8.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerUpKind {
+    Shield,
+    RapidFire,
+    MultiShot,
+    ExtraLife,
+    BunkerRepair,
+}
+impl PowerUpKind {
+    fn color(&self) -> Color {
+        match self {
+            PowerUpKind::Shield => Color::CYAN,
+            PowerUpKind::RapidFire => Color::YELLOW,
+            PowerUpKind::MultiShot => Color::FUCHSIA,
+            PowerUpKind::ExtraLife => Color::LIME_GREEN,
+            PowerUpKind::BunkerRepair => Color::hex("F14F50").unwrap(),
+        }
+    }
+
+    /// Stat changes held for the duration of a timed power-up
+    fn modifiers(&self) -> Vec<StatModifier> {
+        match self {
+            PowerUpKind::Shield => vec![StatModifier::BonusHp(2)],
+            PowerUpKind::RapidFire => vec![
+                StatModifier::CooldownMultiplier(0.4),
+                StatModifier::SpeedMultiplier(1.2),
+            ],
+            PowerUpKind::MultiShot => vec![StatModifier::ExtraProjectiles { count: 2, spread: 0.4 }],
+            PowerUpKind::ExtraLife | PowerUpKind::BunkerRepair => Vec::new(),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+}
+
+/// A reversible change to a player's stats
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+enum StatModifier {
+    BonusHp(u32),
+    CooldownMultiplier(f64),
+    SpeedMultiplier(f32),
+    /// Extra projectiles per shot; `spread` is only used if the weapon had none
+    ExtraProjectiles { count: u32, spread: f32 },
+}
+impl StatModifier {
+    /// Applies the modifier, returning the version of it that `revert` must undo
+    fn apply(self, weapon: &mut WeaponState, movement: &mut MovementModel, health: &mut Health) -> Self {
+        match self {
+            StatModifier::BonusHp(hp) => {
+                health.original_hp += hp;
+                health.current_hp += hp;
+            }
+            StatModifier::CooldownMultiplier(mult) => weapon.weapon.cooldown *= mult,
+            StatModifier::SpeedMultiplier(mult) => movement.max_speed *= mult,
+            StatModifier::ExtraProjectiles { count, spread } => {
+                weapon.weapon.projectile_count += count;
+                if weapon.weapon.spread == 0. {
+                    weapon.weapon.spread = spread;
+                } else {
+                    return StatModifier::ExtraProjectiles { count, spread: 0. };
+                }
+            }
+        }
+        self
+    }
+
+    fn revert(self, weapon: &mut WeaponState, movement: &mut MovementModel, health: &mut Health) {
+        match self {
+            StatModifier::BonusHp(hp) => {
+                // Bonus hit points absorb damage first
+                health.original_hp = health.original_hp.saturating_sub(hp).max(1);
+                health.current_hp = health.current_hp.min(health.original_hp);
+            }
+            StatModifier::CooldownMultiplier(mult) => weapon.weapon.cooldown /= mult,
+            StatModifier::SpeedMultiplier(mult) => movement.max_speed /= mult,
+            StatModifier::ExtraProjectiles { count, spread } => {
+                weapon.weapon.projectile_count = weapon.weapon.projectile_count.saturating_sub(count).max(1);
+                weapon.weapon.spread -= spread;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ActiveModifier {
+    kind: PowerUpKind,
+    modifiers: Vec<StatModifier>,
+    #[serde(with = "crate::savegame::saved_timer")]
+    timer: Timer,
+}
+
+/// Timed power-ups currently held by a player
+#[derive(Component, Clone, Default, Serialize, Deserialize)]
+pub struct ActiveModifiers {
+    active: Vec<ActiveModifier>,
+}
+
+pub struct PowerUpsPlugin;
+impl Plugin for PowerUpsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(drop_power_ups.after("launch_dives"))
+                    .with_system(move_power_ups.label("movement").after("player_shooting"))
+                    .with_system(collect_power_ups.label("collisions").after("projectiles_hit_player"))
+                    .with_system(expire_modifiers.after("collisions"))
+            )
+        ;
+    }
+}
+
+fn drop_power_ups(
+    mut commands: Commands,
+    mut events: EventReader<EnemyDestroyed>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    // Rolled in board order rather than the order the hits were found in,
+    // so a loaded game drops the same power-ups
+    let mut destroyed = events.iter().map(|event| event.position).collect::<Vec<_>>();
+    destroyed.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+
+    let rng = &mut game_rng.0;
+    for position in destroyed {
+        if rng.gen::<f32>() >= DROP_CHANCE {
+            continue;
+        }
+
+        let kind = DROP_TABLE
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .map(|(kind, _)| *kind)
+            .unwrap();
+        spawn_power_up(&mut commands, kind, Vec3::new(position.x, position.y, 4.));
+    }
+}
+
+pub fn spawn_power_up(commands: &mut Commands, kind: PowerUpKind, position: Vec3) {
+    let size = Vec2::new(POWER_UP_SIZE, POWER_UP_SIZE);
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(size),
+                ..Default::default()
+            },
+            transform: Transform {
+                translation: position,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(PowerUp { kind })
+        .insert(RenderedAssetInfo::new(size));
+}
+
+fn move_power_ups(
+    mut commands: Commands,
+    mut q: Query<(Entity, &mut Transform), With<PowerUp>>,
+    win_size: Res<WinSize>,
+) {
+    for (entity, mut tf) in q.iter_mut() {
+        tf.translation.y -= POWER_UP_FALL_SPEED * GAME_TIME_STEP;
+
+        if tf.translation.y < -win_size.h / 2. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn collect_power_ups(
+    mut commands: Commands,
+    mut players: ResMut<Players>,
+    power_up_q: Query<(Entity, &PowerUp, &Transform, &RenderedAssetInfo)>,
+    mut player_q: Query<
+        (&PlayerState, &Transform, &RenderedAssetInfo, &mut WeaponState, &mut MovementModel, &mut Health, &mut ActiveModifiers),
+        With<Player>
+    >,
+    obstacle_q: Query<Entity, With<Obstacle>>,
+    win_size: Res<WinSize>,
+) {
+    for (pu_en, power_up, pu_tf, pu_rai) in power_up_q.iter() {
+        for (player, p_tf, p_rai, mut weapon, mut movement, mut health, mut modifiers) in player_q.iter_mut() {
+            let collision = collide(
+                pu_tf.translation,
+                pu_rai.size,
+                p_tf.translation,
+                p_rai.size,
+            );
+            if collision.is_none() {
+                continue;
+            }
+
+            commands.entity(pu_en).despawn();
+            match power_up.kind {
+                PowerUpKind::ExtraLife => {
+                    if let Some(slot) = players.slots.get_mut(player.index) {
+                        slot.lives += 1;
+                    }
+                }
+                PowerUpKind::BunkerRepair => {
+                    for entity in obstacle_q.iter() {
+                        commands.entity(entity).despawn();
+                    }
+                    spawn_obstacles(&mut commands, &win_size);
+                }
+                kind => {
+                    // Picking up a held power-up only refreshes its timer
+                    let timer = Timer::from_seconds(POWER_UP_DURATION, false);
+                    match modifiers.active.iter_mut().find(|active| active.kind == kind) {
+                        Some(active) => active.timer = timer,
+                        None => {
+                            let applied = kind
+                                .modifiers()
+                                .into_iter()
+                                .map(|modifier| modifier.apply(&mut weapon, &mut movement, &mut health))
+                                .collect();
+                            modifiers.active.push(ActiveModifier { kind, modifiers: applied, timer });
+                        }
+                    }
+                }
+            }
+            break;
+        }
+    }
+}
+
+fn expire_modifiers(
+    time: Res<Time>,
+    mut q: Query<(&mut WeaponState, &mut MovementModel, &mut Health, &mut ActiveModifiers), With<Player>>,
+) {
+    for (mut weapon, mut movement, mut health, mut modifiers) in q.iter_mut() {
+        for active in modifiers.active.iter_mut() {
+            active.timer.tick(time.delta());
+        }
+        if modifiers.active.iter().all(|active| !active.timer.finished()) {
+            continue;
+        }
+
+        let (expired, remaining) = std::mem::take(&mut modifiers.active)
+            .into_iter()
+            .partition::<Vec<_>, _>(|active| active.timer.finished());
+        modifiers.active = remaining;
+
+        for active in expired {
+            for modifier in active.modifiers {
+                modifier.revert(&mut weapon, &mut movement, &mut health);
+            }
+        }
+    }
+}
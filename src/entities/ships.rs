@@ -0,0 +1,59 @@
+use bevy::{prelude::*, reflect::TypeUuid, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::WeaponKind;
+
+use super::{AbilityKind, MovementModel};
+
+/// A player ship, see `assets/data/ships.ships.ron`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShipDef {
+    pub name: String,
+    /// Path under `assets`
+    pub sprite: String,
+    pub hp: u32,
+    pub weapon: WeaponKind,
+    pub ability: AbilityKind,
+    pub movement: MovementModel,
+}
+impl Default for ShipDef {
+    fn default() -> Self {
+        Self {
+            name: "Defender".to_string(),
+            sprite: crate::PLAYER_SPRITE.to_string(),
+            hp: 1,
+            weapon: WeaponKind::Normal,
+            ability: AbilityKind::Shield,
+            movement: MovementModel::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "c3a7f1d2-8e4b-4b6a-9f0e-5d2c7a9b1e83"]
+pub struct ShipDefs {
+    pub ships: Vec<ShipDef>,
+}
+
+/// Sprites of every `ShipDef`, keyed by their path, loaded with the rest
+/// of the assets
+#[derive(Default)]
+pub struct ShipSprites(pub HashMap<String, (Handle<Image>, Vec2)>);
+
+/// Ship picked by a player, with its sprite ready to spawn
+#[derive(Clone, Debug)]
+pub struct Ship {
+    pub def: ShipDef,
+    pub sprite: (Handle<Image>, Vec2),
+}
+impl Ship {
+    /// Resolves the sprite of `def`, falling back to the default player
+    /// sprite for one that failed to load
+    pub fn new(def: ShipDef, ship_sprites: &ShipSprites, fallback: &(Handle<Image>, Vec2)) -> Self {
+        let sprite = ship_sprites.0
+            .get(&def.sprite)
+            .unwrap_or(fallback)
+            .clone();
+        Self { def, sprite }
+    }
+}
@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+
+use crate::config;
+
+const HIGH_SCORES_FILE: &str = "high_scores.ron";
+const HIGH_SCORES_VERSION: u32 = 1;
+pub const HIGH_SCORES_LEN: usize = 10;
+pub const NAME_LEN: usize = 3;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    pub wave: u32,
+}
+
+/// Persisted top scores, best first
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighScores {
+    pub version: u32,
+    pub entries: Vec<HighScore>,
+}
+impl Default for HighScores {
+    fn default() -> Self {
+        Self {
+            version: HIGH_SCORES_VERSION,
+            entries: Vec::new(),
+        }
+    }
+}
+impl HighScores {
+    /// Loads the saved table, starting empty for an unknown version or an
+    /// unreadable file and dropping any entry that doesn't make sense.
+    pub fn load() -> Self {
+        let mut high_scores = config::load_ron::<Self>(HIGH_SCORES_FILE)
+            .filter(|saved| saved.version == HIGH_SCORES_VERSION)
+            .unwrap_or_default();
+
+        high_scores.entries.retain(|entry| entry.score > 0);
+        for entry in high_scores.entries.iter_mut() {
+            entry.name = sanitize_name(&entry.name);
+        }
+        high_scores.entries.sort_by_key(|entry| Reverse(entry.score));
+        high_scores.entries.truncate(HIGH_SCORES_LEN);
+        high_scores
+    }
+
+    pub fn save(&self) {
+        config::save_ron(HIGH_SCORES_FILE, self);
+    }
+
+    /// Whether `score` would make it into the table
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < HIGH_SCORES_LEN
+                || self.entries.last().map(|last| score > last.score).unwrap_or(true))
+    }
+
+    /// Adds an entry below any equal score, returning its rank if it made it
+    pub fn insert(&mut self, name: &str, score: u32, wave: u32) -> Option<usize> {
+        if !self.qualifies(score) {
+            return None;
+        }
+
+        let rank = self
+            .entries
+            .iter()
+            .position(|entry| score > entry.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(rank, HighScore {
+            name: sanitize_name(name),
+            score,
+            wave,
+        });
+        self.entries.truncate(HIGH_SCORES_LEN);
+        Some(rank)
+    }
+}
+
+/// Three upper case letters, padded with `A`
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_uppercase())
+        .chain(std::iter::repeat('A'))
+        .take(NAME_LEN)
+        .collect()
+}
+
+pub struct HighScoresPlugin;
+impl Plugin for HighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(HighScores::load())
+        ;
+    }
+}
@@ -0,0 +1,232 @@
+#[cfg(feature = "embedded_assets")]
+mod embedded;
+
+use std::marker::PhantomData;
+
+use bevy::{
+    prelude::*,
+    asset::{Asset, AssetLoader, HandleId, LoadContext, LoadState, LoadedAsset},
+    utils::BoxedFuture,
+};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    GameState, SpriteInfos, AudioInfos, FontInfos, DataInfos,
+    entities::{BoardScene, DivePaths, ShipDefs, ShipSprites},
+    PLAYER_SPRITE, RED_ENEMY_SPRITE, GREEN_ENEMY_SPRITE, YELLOW_ENEMY_SPRITE,
+    TOP_EXTRA_ENEMY_SPRITE, PLAYER_LASER_SPRITE, ENEMY_LASER_SPRITE,
+    LASER_SOUND, EXPLOSION_SOUND, MAIN_FONT, DIVE_PATHS, SHIPS,
+};
+
+#[cfg(feature = "embedded_assets")]
+pub use self::embedded::EmbeddedAssetsPlugin;
+
+/// Every handle requested from the AssetServer at startup.
+/// Gameplay does not start until all of them report `LoadState::Loaded`.
+pub struct AssetManifest {
+    player: Handle<Image>,
+    red_enemy: Handle<Image>,
+    green_enemy: Handle<Image>,
+    yellow_enemy: Handle<Image>,
+    top_alien: Handle<Image>,
+    player_laser: Handle<Image>,
+    alien_laser: Handle<Image>,
+    laser_sound: Handle<AudioSource>,
+    explosion_sound: Handle<AudioSource>,
+    main_font: Handle<Font>,
+    dive_paths: Handle<DivePaths>,
+    ships: Handle<ShipDefs>,
+    board_scene: Option<Handle<DynamicScene>>,
+    /// Requested once `ships` is loaded, by sprite path
+    ship_sprites: Option<Vec<(String, Handle<Image>)>>,
+}
+impl AssetManifest {
+    fn load(asset_server: &AssetServer) -> Self {
+        Self {
+            player: asset_server.load(PLAYER_SPRITE),
+            red_enemy: asset_server.load(RED_ENEMY_SPRITE),
+            green_enemy: asset_server.load(GREEN_ENEMY_SPRITE),
+            yellow_enemy: asset_server.load(YELLOW_ENEMY_SPRITE),
+            top_alien: asset_server.load(TOP_EXTRA_ENEMY_SPRITE),
+            player_laser: asset_server.load(PLAYER_LASER_SPRITE),
+            alien_laser: asset_server.load(ENEMY_LASER_SPRITE),
+            laser_sound: asset_server.load(LASER_SOUND),
+            explosion_sound: asset_server.load(EXPLOSION_SOUND),
+            main_font: asset_server.load(MAIN_FONT),
+            dive_paths: asset_server.load(DIVE_PATHS),
+            ships: asset_server.load(SHIPS),
+            board_scene: BoardScene::path_from_args().map(|path| asset_server.load(path.as_str())),
+            ship_sprites: None,
+        }
+    }
+
+    fn ids(&self) -> Vec<HandleId> {
+        let mut ids = vec![
+            self.player.id,
+            self.red_enemy.id,
+            self.green_enemy.id,
+            self.yellow_enemy.id,
+            self.top_alien.id,
+            self.player_laser.id,
+            self.alien_laser.id,
+            self.laser_sound.id,
+            self.explosion_sound.id,
+            self.main_font.id,
+            self.dive_paths.id,
+            self.ships.id,
+        ];
+        ids.extend(self.board_scene.as_ref().map(|handle| handle.id));
+        ids
+    }
+}
+
+/// Loads a data asset from a RON file
+pub struct RonAssetLoader<T> {
+    extensions: &'static [&'static str],
+    _asset: PhantomData<fn() -> T>,
+}
+impl<T> RonAssetLoader<T> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            _asset: PhantomData,
+        }
+    }
+}
+impl<T: Asset + DeserializeOwned> AssetLoader for RonAssetLoader<T> {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let asset = ron::de::from_bytes::<T>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
+
+pub struct LoadingPlugin;
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_asset::<DivePaths>()
+            .add_asset_loader(RonAssetLoader::<DivePaths>::new(&["dives.ron"]))
+            .add_asset::<ShipDefs>()
+            .add_asset_loader(RonAssetLoader::<ShipDefs>::new(&["ships.ron"]))
+            .add_startup_system(load_assets)
+            .add_system_set(
+                SystemSet::on_update(GameState::Loading)
+                    .with_system(check_assets_loaded)
+            )
+        ;
+    }
+}
+
+fn load_assets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    // Hot reload sprites, sounds, fonts and data while developing
+    #[cfg(debug_assertions)]
+    if let Err(err) = asset_server.watch_for_changes() {
+        warn!("Asset hot reloading unavailable: {:?}", err);
+    }
+
+    commands.insert_resource(AssetManifest::load(&asset_server));
+}
+
+fn check_assets_loaded(
+    mut commands: Commands,
+    mut state: ResMut<State<GameState>>,
+    mut manifest: ResMut<AssetManifest>,
+    asset_server: Res<AssetServer>,
+    images: Res<Assets<Image>>,
+    ship_defs: Res<Assets<ShipDefs>>,
+) {
+    match asset_server.get_group_load_state(manifest.ids()) {
+        LoadState::Loaded => {}
+        LoadState::Failed => {
+            for id in manifest.ids() {
+                if let LoadState::Failed = asset_server.get_load_state(id) {
+                    error!("Failed to load asset {:?}", asset_server.get_handle_path(id));
+                }
+            }
+            return;
+        }
+        _ => return,
+    }
+
+    // Ship sprites are only known once the ships are
+    let manifest = &mut *manifest;
+    let ship_sprites = manifest.ship_sprites.get_or_insert_with(|| {
+        ship_defs
+            .get(&manifest.ships)
+            .map(|defs| {
+                defs.ships
+                    .iter()
+                    .map(|def| (def.sprite.clone(), asset_server.load(def.sprite.as_str())))
+                    .collect()
+            })
+            .unwrap_or_default()
+    });
+    let ship_sprites_loading = ship_sprites
+        .iter()
+        .any(|(_, handle)| matches!(asset_server.get_load_state(handle), LoadState::NotLoaded | LoadState::Loading));
+    if ship_sprites_loading {
+        return;
+    }
+
+    // Sizes are only known once the image has been decoded
+    let image_size = |handle: &Handle<Image>| {
+        images.get(handle).map(|image| {
+            let size = image.texture_descriptor.size;
+            Vec2::new(size.width as f32, size.height as f32)
+        })
+    };
+    let sprite = |handle: &Handle<Image>| {
+        let size = image_size(handle).expect("Loaded image missing from Assets<Image>");
+        (handle.clone(), size)
+    };
+
+    commands.insert_resource(SpriteInfos {
+        player: sprite(&manifest.player),
+        red_enemy: sprite(&manifest.red_enemy),
+        green_enemy: sprite(&manifest.green_enemy),
+        yellow_enemy: sprite(&manifest.yellow_enemy),
+        player_laser: sprite(&manifest.player_laser),
+        alien_laser: sprite(&manifest.alien_laser),
+        top_alien: sprite(&manifest.top_alien),
+    });
+    commands.insert_resource(AudioInfos {
+        laser: manifest.laser_sound.clone(),
+        explosion: manifest.explosion_sound.clone(),
+    });
+    commands.insert_resource(FontInfos {
+        main: manifest.main_font.clone(),
+    });
+    commands.insert_resource(DataInfos {
+        dive_paths: manifest.dive_paths.clone(),
+        ships: manifest.ships.clone(),
+    });
+    commands.insert_resource(BoardScene(manifest.board_scene.clone()));
+
+    // A ship whose sprite failed to load flies with the default one
+    let mut sprites = ShipSprites::default();
+    for (path, handle) in ship_sprites.iter() {
+        match image_size(handle) {
+            Some(size) => {
+                sprites.0.insert(path.clone(), (handle.clone(), size));
+            }
+            None => warn!("Failed to load ship sprite {}, using the default one", path),
+        }
+    }
+    commands.insert_resource(sprites);
+
+    state.set(GameState::MainMenu).unwrap();
+}
@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    prelude::*,
+    asset::{AssetIo, AssetIoError},
+    tasks::IoTaskPool,
+    utils::BoxedFuture,
+};
+
+use crate::{
+    PLAYER_SPRITE, RED_ENEMY_SPRITE, GREEN_ENEMY_SPRITE, YELLOW_ENEMY_SPRITE,
+    TOP_EXTRA_ENEMY_SPRITE, PLAYER_LASER_SPRITE, ENEMY_LASER_SPRITE,
+    LASER_SOUND, EXPLOSION_SOUND, MAIN_FONT, DIVE_PATHS, SHIPS,
+};
+
+/// Asset bytes compiled into the binary, keyed by their path under `assets`.
+const EMBEDDED_ASSETS: &[(&str, &[u8])] = &[
+    (PLAYER_SPRITE, include_bytes!("../../assets/graphics/player.png")),
+    ("graphics/interceptor.png", include_bytes!("../../assets/graphics/interceptor.png")),
+    ("graphics/bulwark.png", include_bytes!("../../assets/graphics/bulwark.png")),
+    ("graphics/lancer.png", include_bytes!("../../assets/graphics/lancer.png")),
+    (RED_ENEMY_SPRITE, include_bytes!("../../assets/graphics/red.png")),
+    (GREEN_ENEMY_SPRITE, include_bytes!("../../assets/graphics/green.png")),
+    (YELLOW_ENEMY_SPRITE, include_bytes!("../../assets/graphics/yellow.png")),
+    (TOP_EXTRA_ENEMY_SPRITE, include_bytes!("../../assets/graphics/extra.png")),
+    (PLAYER_LASER_SPRITE, include_bytes!("../../assets/graphics/laser_a_01.png")),
+    (ENEMY_LASER_SPRITE, include_bytes!("../../assets/graphics/laser_b_01.png")),
+    (LASER_SOUND, include_bytes!("../../assets/audio/laser.wav")),
+    (EXPLOSION_SOUND, include_bytes!("../../assets/audio/explosion.wav")),
+    (MAIN_FONT, include_bytes!("../../assets/font/Pixeled.ttf")),
+    (DIVE_PATHS, include_bytes!("../../assets/data/dive_paths.dives.ron")),
+    (SHIPS, include_bytes!("../../assets/data/ships.ships.ron")),
+];
+
+/// Serves embedded assets, deferring to the platform AssetIo
+/// for anything not compiled in.
+struct EmbeddedAssetIo {
+    fallback: Box<dyn AssetIo>,
+}
+impl EmbeddedAssetIo {
+    fn embedded(path: &Path) -> Option<&'static [u8]> {
+        EMBEDDED_ASSETS
+            .iter()
+            .find(|(asset_path, _)| Path::new(asset_path) == path)
+            .map(|(_, bytes)| *bytes)
+    }
+}
+impl AssetIo for EmbeddedAssetIo {
+    fn load_path<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        match Self::embedded(path) {
+            Some(bytes) => Box::pin(async move { Ok(bytes.to_vec()) }),
+            None => self.fallback.load_path(path),
+        }
+    }
+
+    fn read_directory(
+        &self,
+        path: &Path,
+    ) -> Result<Box<dyn Iterator<Item = PathBuf>>, AssetIoError> {
+        self.fallback.read_directory(path)
+    }
+
+    fn is_directory(&self, path: &Path) -> bool {
+        self.fallback.is_directory(path)
+    }
+
+    fn watch_path_for_changes(&self, path: &Path) -> Result<(), AssetIoError> {
+        self.fallback.watch_path_for_changes(path)
+    }
+
+    fn watch_for_changes(&self) -> Result<(), AssetIoError> {
+        self.fallback.watch_for_changes()
+    }
+}
+
+/// Replaces the AssetServer with one backed by `EmbeddedAssetIo`.
+/// Must be added before bevy's `AssetPlugin`.
+pub struct EmbeddedAssetsPlugin;
+impl Plugin for EmbeddedAssetsPlugin {
+    fn build(&self, app: &mut App) {
+        let asset_io = EmbeddedAssetIo {
+            fallback: bevy::asset::create_platform_default_asset_io(app),
+        };
+        let task_pool = app
+            .world
+            .get_resource::<IoTaskPool>()
+            .expect("IoTaskPool is inserted by CorePlugin")
+            .0
+            .clone();
+
+        app.insert_resource(AssetServer::new(asset_io, task_pool));
+    }
+}
@@ -1,16 +1,31 @@
 // region:      Modules
 mod system;
-mod utils;
+mod config;
+mod controls;
+mod difficulty;
 mod diagnostics;
+mod high_scores;
+mod loading;
+mod savegame;
+mod settings;
 mod entities;
 mod shared;
+mod ui;
 // endregion:   Modules
 
 use bevy::{prelude::*};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+use controls::ControlsPlugin;
+use difficulty::DifficultyPlugin;
 use diagnostics::DiagnosticsPluginGroup;
 use entities::EntitiesPluginGroup;
+use high_scores::HighScoresPlugin;
+use loading::LoadingPlugin;
+use savegame::SaveGamePlugin;
+use settings::{Settings, SettingsPlugin};
 use shared::SharedPluginGroup;
-use utils::load_image;
+use ui::UiPluginGroup;
 
 // region:      Constants
 const WINDOW_WIDTH: f32 = 600.0;
@@ -19,36 +34,84 @@ const GAME_TIME_STEP: f32 = 1. / 60.;
 // endregion:   Constants
 
 // region:      Assets
-const SPRITE_DIR: &str = "assets/graphics";
-const AUDIO_DIR: &str = "assets/audio";
-const FONT_DIR: &str = "assets/font";
-
-const PLAYER_SPRITE: &str = "player.png";
-const RED_ENEMY_SPRITE: &str = "red.png";
-const GREEN_ENEMY_SPRITE: &str = "green.png";
-const YELLOW_ENEMY_SPRITE: &str = "yellow.png";
-const TOP_EXTRA_ENEMY_SPRITE: &str = "extra.png";
-
-const PLAYER_LASER_SPRITE: &str = "laser_a_01.png";
-const ENEMY_LASER_SPRITE: &str = "laser_b_01.png";
+// Paths are relative to the `assets` folder served by the AssetServer
+const PLAYER_SPRITE: &str = "graphics/player.png";
+const RED_ENEMY_SPRITE: &str = "graphics/red.png";
+const GREEN_ENEMY_SPRITE: &str = "graphics/green.png";
+const YELLOW_ENEMY_SPRITE: &str = "graphics/yellow.png";
+const TOP_EXTRA_ENEMY_SPRITE: &str = "graphics/extra.png";
+
+const PLAYER_LASER_SPRITE: &str = "graphics/laser_a_01.png";
+const ENEMY_LASER_SPRITE: &str = "graphics/laser_b_01.png";
+
+const LASER_SOUND: &str = "audio/laser.wav";
+const EXPLOSION_SOUND: &str = "audio/explosion.wav";
+
+const MAIN_FONT: &str = "font/Pixeled.ttf";
+
+const DIVE_PATHS: &str = "data/dive_paths.dives.ron";
+const SHIPS: &str = "data/ships.ships.ron";
 // endregion:   Assets
 
 // region:      States
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 enum GameState {
+    Loading,
     MainMenu,
+    ShipSelect,
     InGame,
     Paused,
+    Settings,
+    Rebinding,
     GameOver,
 }
+
+// Systems of an `InGame` frame run in a fixed order, labelled
+// "weapons_state", "player_movement", "player_shooting", "movement",
+// "collisions", then "player_lives" and "wave_progress", "fleet_fire" and
+// "launch_dives". Left alone Bevy picks another order on every launch, and
+// a loaded game would no longer play out like the saved one.
 // endregion:   States
 
 // region:      Resources
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    #[default]
+    SinglePlayer,
+    /// Two ships on screen at once
+    Coop,
+    /// Players take turns, each on their own board
+    Alternating,
+}
+
 #[derive(Default)]
 pub struct Game {
     active_enemies: i32,
+    /// Wave of the board currently in play, starting at 1
+    wave: u32,
 }
 
+/// Randomness of everything in play, seeded so a saved game carries on the
+/// same way once loaded
+pub struct GameRng(pub StdRng);
+impl Default for GameRng {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+impl GameRng {
+    /// Reseeds from the current stream and returns the seed, restoring it
+    /// later replays everything drawn from here on
+    pub fn reseed(&mut self) -> u64 {
+        let seed = self.0.gen();
+        self.0 = StdRng::seed_from_u64(seed);
+        seed
+    }
+
+    pub fn from_seed(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
 
 pub struct WinSize {
     w: f32,
@@ -86,6 +149,24 @@ pub struct SpriteInfos {
     alien_laser: (Handle<Image>, Vec2),
     top_alien: (Handle<Image>, Vec2),
 }
+
+#[derive(Debug)]
+pub struct AudioInfos {
+    laser: Handle<AudioSource>,
+    explosion: Handle<AudioSource>,
+}
+
+#[derive(Debug)]
+pub struct FontInfos {
+    main: Handle<Font>,
+}
+
+/// Gameplay data loaded as assets, so it hot reloads like the rest
+#[derive(Debug)]
+pub struct DataInfos {
+    dive_paths: Handle<entities::DivePaths>,
+    ships: Handle<entities::ShipDefs>,
+}
 // endregion:   Resources
 
 // region:      Components
@@ -95,25 +176,40 @@ pub struct SpriteInfos {
 // endregion:   Entities
 
 fn main() {
+    let settings = Settings::load();
+
     App::new()
         // Initial setup
-        .add_state(GameState::InGame)
+        .add_state(GameState::Loading)
         .init_resource::<Game>()
-        .init_resource::<WinSize>()
+        .init_resource::<GameRng>()
+        .init_resource::<GameMode>()
+        .insert_resource(WinSize {
+            w: settings.window_width,
+            h: settings.window_height,
+        })
         .init_resource::<AssetScaling>()
         .insert_resource(ClearColor(Color::rgb(0.04, 0.04, 0.04)))
-        .insert_resource(WindowDescriptor {
-            title: "".to_string(),
-            width: WINDOW_WIDTH,
-            height: WINDOW_HEIGHT,
-            ..Default::default()
+        .insert_resource(settings.window_descriptor())
+        .insert_resource(settings)
+        .add_plugins_with(DefaultPlugins, |group| {
+            // Serve assets compiled into the binary instead of the `assets` folder
+            #[cfg(feature = "embedded_assets")]
+            group.add_before::<bevy::asset::AssetPlugin, _>(loading::EmbeddedAssetsPlugin);
+            group
         })
-        .add_plugins(DefaultPlugins)
         // Insert builtins
         .add_startup_system(setup.label("main_setup"))
+        .add_plugin(LoadingPlugin)
+        .add_plugin(ControlsPlugin)
+        .add_plugin(DifficultyPlugin)
+        .add_plugin(HighScoresPlugin)
+        .add_plugin(SettingsPlugin)
+        .add_plugin(SaveGamePlugin)
         .add_plugins(DiagnosticsPluginGroup)// Debug
         .add_plugins(SharedPluginGroup)// Debug
         .add_plugins(EntitiesPluginGroup)
+        .add_plugins(UiPluginGroup)
 
         .run()
 }
@@ -121,7 +217,6 @@ fn main() {
 fn setup(
     mut commands: Commands,
     mut windows: ResMut<Windows>,
-    mut images: ResMut<Assets<Image>>,
 ) {
     let v1 = Vec2::new(12., 12.);
     let v2 = Vec2::new(12., 12.);
@@ -131,21 +226,11 @@ fn setup(
     println!("Main setup");
     // camera
     commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands.spawn_bundle(UiCameraBundle::default());
 
     // set window
     let window = windows.get_primary_mut().unwrap();
 
-    // Create main resources
-    commands.insert_resource(SpriteInfos {
-        player: load_image(&mut images, SPRITE_DIR, PLAYER_SPRITE),
-        red_enemy: load_image(&mut images, SPRITE_DIR, RED_ENEMY_SPRITE),
-        green_enemy: load_image(&mut images, SPRITE_DIR, GREEN_ENEMY_SPRITE),
-        yellow_enemy: load_image(&mut images, SPRITE_DIR, YELLOW_ENEMY_SPRITE),
-        player_laser: load_image(&mut images, SPRITE_DIR, PLAYER_LASER_SPRITE),
-        alien_laser: load_image(&mut images, SPRITE_DIR, ENEMY_LASER_SPRITE),
-        top_alien: load_image(&mut images, SPRITE_DIR, TOP_EXTRA_ENEMY_SPRITE),
-    });
-
     // position window
     window.set_position(IVec2::new(0, 0));
 }
\ No newline at end of file
@@ -0,0 +1,642 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Game, GameMode, GameRng, GameState, SpriteInfos, config,
+    difficulty::{Difficulty, DifficultyDirector, DifficultyPreset},
+    entities::{Ability, ActiveModifiers, AlienState, BoardQuery, BoardSnapshot, DiveAttacks, FleetFire, FromEnemy, FromPlayer, InGameEntity, MovementModel, Player, PlayerState, Players, PowerUp, PowerUpKind, Ship, ShipDef, ShipSprites, restore_board, spawn_player, spawn_power_up},
+    shared::{Health, Homing, Motion, MotionPattern, Projectile, RenderedAssetInfo, Velocity, WeaponState},
+};
+
+const SAVEGAME_FILE: &str = "savegame.ron";
+const SAVEGAME_VERSION: u32 = 2;
+
+/// Serde for `Timer`, which has none of its own, through
+/// `#[serde(with = "crate::savegame::saved_timer")]`
+pub mod saved_timer {
+    use std::time::Duration;
+
+    use bevy::prelude::Timer;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct SavedTimer {
+        duration: Duration,
+        elapsed: Duration,
+        repeating: bool,
+    }
+    impl From<&Timer> for SavedTimer {
+        fn from(timer: &Timer) -> Self {
+            Self {
+                duration: timer.duration(),
+                elapsed: timer.elapsed(),
+                repeating: timer.repeating(),
+            }
+        }
+    }
+    impl From<SavedTimer> for Timer {
+        fn from(saved: SavedTimer) -> Self {
+            let mut timer = Timer::new(saved.duration, saved.repeating);
+            timer.tick(saved.elapsed);
+            timer
+        }
+    }
+
+    pub fn serialize<S: Serializer>(timer: &Timer, serializer: S) -> Result<S::Ok, S::Error> {
+        SavedTimer::from(timer).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Timer, D::Error> {
+        SavedTimer::deserialize(deserializer).map(Timer::from)
+    }
+
+    /// Same for an `Option<Timer>`
+    pub mod option {
+        use bevy::prelude::Timer;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        use super::SavedTimer;
+
+        pub fn serialize<S: Serializer>(timer: &Option<Timer>, serializer: S) -> Result<S::Ok, S::Error> {
+            timer.as_ref().map(SavedTimer::from).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Timer>, D::Error> {
+            Option::<SavedTimer>::deserialize(deserializer).map(|saved| saved.map(Timer::from))
+        }
+    }
+}
+
+/// A ship on the board when the game was saved, with its stats as changed
+/// by the power-ups it holds
+#[derive(Serialize, Deserialize)]
+pub struct ShipSave {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub health: Health,
+    pub weapon: WeaponState,
+    pub movement: MovementModel,
+    pub ability: Ability,
+    pub modifiers: ActiveModifiers,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlayerSave {
+    pub lives: u32,
+    pub score: u32,
+    pub ship_def: Option<ShipDef>,
+    /// Board put aside while alternating
+    pub board: Option<BoardSnapshot>,
+    /// `None` while waiting to respawn or out of play
+    pub ship: Option<ShipSave>,
+    /// Whether the ship is on the board or was lost on the saved frame
+    pub alive: bool,
+    #[serde(with = "saved_timer::option")]
+    pub respawn: Option<Timer>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ProjectileSave {
+    pub position: Vec3,
+    pub velocity: Vec2,
+    pub size: Vec2,
+    pub tint: Color,
+    pub damage: u32,
+    pub pierce: u32,
+    /// Enemies already passed through, indices into `BoardQuery::enemies`
+    pub pierced: Vec<usize>,
+    pub strength: u32,
+    /// Shooter when fired by a player, an alien otherwise
+    pub from_player: Option<usize>,
+    pub motion: Option<(MotionPattern, f32)>,
+    pub homing: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PowerUpSave {
+    pub kind: PowerUpKind,
+    pub position: Vec3,
+}
+
+/// A game saved from the pause menu, down to its timers, so a loaded game
+/// plays out the way the saved one would have. Only explosions aren't kept.
+#[derive(Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    pub mode: GameMode,
+    pub difficulty: DifficultyPreset,
+    pub director: DifficultyDirector,
+    pub turn: usize,
+    /// `GameRng` is reseeded with this when saving, so a loaded game draws
+    /// the same numbers the saved one went on to
+    pub seed: u64,
+    pub board: BoardSnapshot,
+    pub fleet_fire: FleetFire,
+    pub dive_attacks: DiveAttacks,
+    pub players: Vec<PlayerSave>,
+    pub projectiles: Vec<ProjectileSave>,
+    pub power_ups: Vec<PowerUpSave>,
+}
+impl SaveGame {
+    /// Saved game, `None` if there is none or it can't be read
+    pub fn load() -> Option<Self> {
+        config::load_ron::<Self>(SAVEGAME_FILE)
+            .filter(|saved| saved.version == SAVEGAME_VERSION)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        config::write_ron(SAVEGAME_FILE, self)
+    }
+
+    pub fn exists() -> bool {
+        config::config_path(SAVEGAME_FILE)
+            .map(|path| path.exists())
+            .unwrap_or(false)
+    }
+}
+
+/// Sent to save the game in play
+pub struct SaveGameRequested;
+
+/// Sent once a requested save is done, with the error if it failed
+pub struct GameSaved(pub Result<(), String>);
+
+/// Save to put in play, the game setup leaves the board empty while one is
+/// pending
+#[derive(Default)]
+pub struct PendingLoad(pub Option<SaveGame>);
+
+/// Everything a `SaveGame` is taken from
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub struct GameSnapshot<'w, 's> {
+    game_rng: ResMut<'w, GameRng>,
+    game: Res<'w, Game>,
+    alien_state: Option<Res<'w, AlienState>>,
+    players: Res<'w, Players>,
+    difficulty: Res<'w, Difficulty>,
+    director: Res<'w, DifficultyDirector>,
+    fleet_fire: Res<'w, FleetFire>,
+    dive_attacks: Res<'w, DiveAttacks>,
+    board_q: BoardQuery<'w, 's>,
+    player_q: Query<'w, 's, (
+        &'static PlayerState,
+        &'static Transform,
+        &'static Velocity,
+        &'static Health,
+        &'static WeaponState,
+        &'static MovementModel,
+        &'static Ability,
+        &'static ActiveModifiers,
+    ), With<Player>>,
+    projectile_q: Query<'w, 's, (
+        &'static Projectile,
+        &'static Velocity,
+        &'static Transform,
+        &'static RenderedAssetInfo,
+        &'static Sprite,
+        Option<&'static FromPlayer>,
+        Option<&'static Motion>,
+        Option<&'static Homing>,
+    )>,
+    power_up_q: Query<'w, 's, (&'static PowerUp, &'static Transform)>,
+}
+impl<'w, 's> GameSnapshot<'w, 's> {
+    /// The game in play, `None` outside of a game. Reseeds `GameRng`.
+    pub fn take(&mut self) -> Option<SaveGame> {
+        let alien_state = self.alien_state.as_ref()?;
+
+        let players = self.players
+            .slots
+            .iter()
+            .enumerate()
+            .map(|(index, slot)| PlayerSave {
+                lives: slot.lives,
+                score: slot.score,
+                ship_def: slot.ship.as_ref().map(|ship| ship.def.clone()),
+                board: slot.board.clone(),
+                ship: self.player_q
+                    .iter()
+                    .find(|(player, ..)| player.index == index)
+                    .map(|(_, tf, vel, health, weapon, movement, ability, modifiers)| ShipSave {
+                        position: tf.translation.truncate(),
+                        velocity: vel.value,
+                        health: health.clone(),
+                        weapon: weapon.clone(),
+                        movement: movement.clone(),
+                        ability: ability.clone(),
+                        modifiers: modifiers.clone(),
+                    }),
+                alive: slot.is_alive(),
+                respawn: slot.respawn_timer().cloned(),
+            })
+            .collect();
+
+        let enemies = self.board_q.enemies();
+        let projectiles = self.projectile_q
+            .iter()
+            .map(|(projectile, vel, tf, rai, sprite, from_player, motion, homing)| ProjectileSave {
+                position: tf.translation,
+                velocity: vel.value,
+                size: rai.size,
+                tint: sprite.color,
+                damage: projectile.damage,
+                pierce: projectile.pierce,
+                pierced: projectile.pierced
+                    .iter()
+                    .filter_map(|pierced| enemies.iter().position(|enemy| enemy == pierced))
+                    .collect(),
+                strength: projectile.strength,
+                from_player: from_player.map(|from| from.player),
+                motion: motion.map(|motion| (motion.pattern, motion.elapsed)),
+                homing: homing.map(|homing| homing.turn_rate),
+            })
+            .collect();
+
+        let power_ups = self.power_up_q
+            .iter()
+            .map(|(power_up, tf)| PowerUpSave {
+                kind: power_up.kind,
+                position: tf.translation,
+            })
+            .collect();
+
+        Some(SaveGame {
+            version: SAVEGAME_VERSION,
+            mode: self.players.mode,
+            difficulty: self.difficulty.preset,
+            director: self.director.clone(),
+            turn: self.players.turn,
+            seed: self.game_rng.reseed(),
+            board: self.board_q.snapshot(self.game.wave, alien_state, true),
+            fleet_fire: self.fleet_fire.clone(),
+            dive_attacks: self.dive_attacks.clone(),
+            players,
+            projectiles,
+            power_ups,
+        })
+    }
+}
+
+pub struct SaveGamePlugin;
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<SaveGameRequested>()
+            .add_event::<GameSaved>()
+            .init_resource::<PendingLoad>()
+            // Saving happens from the pause menu, so in any state
+            .add_system(save_game)
+            // Ahead of the game systems so they never see a half loaded board
+            .add_system_to_stage(CoreStage::PreUpdate, load_game)
+        ;
+    }
+}
+
+fn save_game(
+    mut events: EventReader<SaveGameRequested>,
+    mut saved_events: EventWriter<GameSaved>,
+    mut snapshot: GameSnapshot,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+
+    let result = match snapshot.take() {
+        Some(save) => save.save().map(|_| info!("Game saved on wave {}", save.board.wave)),
+        None => Err("no game in play".to_string()),
+    };
+    if let Err(err) = &result {
+        error!("Failed to save the game: {}", err);
+    }
+    saved_events.send(GameSaved(result));
+}
+
+/// Swaps whatever is in play for the pending save
+#[allow(clippy::too_many_arguments)]
+fn load_game(
+    mut commands: Commands,
+    mut pending: ResMut<PendingLoad>,
+    mut game: ResMut<Game>,
+    mut game_mode: ResMut<GameMode>,
+    mut players: ResMut<Players>,
+    mut difficulty: ResMut<Difficulty>,
+    mut director: ResMut<DifficultyDirector>,
+    mut game_rng: ResMut<GameRng>,
+    mut fleet_fire: ResMut<FleetFire>,
+    mut dive_attacks: ResMut<DiveAttacks>,
+    state: Res<State<GameState>>,
+    q: Query<Entity, InGameEntity>,
+    ship_sprites: Res<ShipSprites>,
+    sprite_infos: Res<SpriteInfos>,
+) {
+    if state.current() != &GameState::InGame {
+        return;
+    }
+    let save = match pending.0.take() {
+        Some(save) => save,
+        None => return,
+    };
+
+    for entity in q.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    *game_mode = save.mode;
+    *difficulty = Difficulty::from_preset(save.difficulty).adjusted(save.director.level);
+    *director = save.director;
+    *game_rng = GameRng::from_seed(save.seed);
+    *fleet_fire = save.fleet_fire;
+    *dive_attacks = save.dive_attacks;
+
+    game.wave = save.board.wave;
+    commands.insert_resource(save.board.alien_state.clone());
+    let enemies = restore_board(&mut commands, save.board, &sprite_infos);
+
+    *players = Players::new(save.mode);
+    players.turn = save.turn;
+    for (index, player) in save.players.into_iter().enumerate().take(players.slots.len()) {
+        let slot = &mut players.slots[index];
+        slot.lives = player.lives;
+        slot.score = player.score;
+        slot.board = player.board;
+        slot.ship = player.ship_def.map(|def| Ship::new(def, &ship_sprites, &sprite_infos.player));
+
+        if let Some(ship) = player.ship {
+            let entity = spawn_player(&mut commands, &sprite_infos, index, ship.position, slot);
+            commands
+                .entity(entity)
+                .insert(Velocity { value: ship.velocity })
+                .insert(ship.health)
+                .insert(ship.weapon)
+                .insert(ship.movement)
+                .insert(ship.ability)
+                .insert(ship.modifiers);
+        }
+        slot.restore(player.alive, player.respawn);
+    }
+
+    for projectile in save.projectiles {
+        spawn_saved_projectile(&mut commands, &projectile, &enemies, &sprite_infos);
+    }
+    for power_up in save.power_ups {
+        spawn_power_up(&mut commands, power_up.kind, power_up.position);
+    }
+    info!("Game loaded on wave {}", game.wave);
+}
+
+fn spawn_saved_projectile(commands: &mut Commands, saved: &ProjectileSave, enemies: &[Entity], sprite_infos: &SpriteInfos) {
+    let texture = match saved.from_player {
+        Some(_) => sprite_infos.player_laser.0.clone(),
+        None => sprite_infos.alien_laser.0.clone(),
+    };
+
+    let mut projectile = commands.spawn();
+    projectile
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: saved.tint,
+                custom_size: Some(saved.size),
+                ..Default::default()
+            },
+            texture,
+            transform: Transform {
+                rotation: Quat::from_rotation_z((-saved.velocity.x).atan2(saved.velocity.y)),
+                translation: saved.position,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(RenderedAssetInfo::new(saved.size))
+        .insert(Projectile {
+            damage: saved.damage,
+            pierce: saved.pierce,
+            pierced: saved.pierced
+                .iter()
+                .filter_map(|&index| enemies.get(index).copied())
+                .collect(),
+            strength: saved.strength,
+        })
+        .insert(Velocity { value: saved.velocity });
+
+    match saved.from_player {
+        Some(player) => projectile.insert(FromPlayer { player }),
+        None => projectile.insert(FromEnemy),
+    };
+    if let Some((pattern, elapsed)) = saved.motion {
+        projectile.insert(Motion { pattern, elapsed });
+    }
+    if let Some(turn_rate) = saved.homing {
+        projectile.insert(Homing { turn_rate });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{
+        asset::AssetPlugin,
+        ecs::{schedule::SingleThreadedExecutor, system::SystemState},
+        input::InputPlugin,
+        scene::DynamicScene,
+        tasks::{IoTaskPool, TaskPool},
+        transform::TransformPlugin,
+    };
+    use serde::Serialize;
+
+    use crate::{AssetScaling, AudioInfos, DataInfos, GAME_TIME_STEP, WinSize, controls::{ControlBindings, ControlsPlugin}, difficulty::DifficultyPlugin, entities::{DivePaths, Diving, EntitiesPluginGroup, ShipDefs}, settings::Settings, shared::SharedPluginGroup};
+
+    use super::*;
+
+    /// Most frames played waiting for the first dive
+    const MAX_FRAMES_BEFORE_DIVE: u32 = 600;
+    /// Frames played into the dive before saving
+    const FRAMES_INTO_DIVE: u32 = 30;
+    /// Frames played from the save, by the saved game and by the loaded one
+    const FRAMES_AFTER_SAVE: u32 = 300;
+
+    /// A frame long step of the clock, the same for every frame so both
+    /// games see the same time go by
+    fn frame_time() -> Time {
+        let mut time = Time::default();
+        time.update();
+        std::thread::sleep(Duration::from_secs_f32(GAME_TIME_STEP));
+        time.update();
+        time
+    }
+
+    /// The game systems run headless, one system at a time
+    struct TestGame {
+        app: App,
+        frame_time: Time,
+    }
+    impl TestGame {
+        fn new(frame_time: &Time) -> Self {
+            let mut app = App::new();
+            app
+                .insert_resource(IoTaskPool(TaskPool::new()))
+                .add_plugin(AssetPlugin)
+                .add_plugin(TransformPlugin)
+                .add_plugin(InputPlugin)
+                .add_asset::<Image>()
+                .add_asset::<AudioSource>()
+                .add_asset::<DynamicScene>()
+                .add_asset::<DivePaths>()
+                .add_asset::<ShipDefs>()
+                .add_state(GameState::MainMenu)
+                .insert_resource(GameRng::from_seed(7))
+                .init_resource::<Game>()
+                .init_resource::<GameMode>()
+                .init_resource::<WinSize>()
+                .init_resource::<AssetScaling>()
+                .init_resource::<Settings>()
+                .init_resource::<Audio>()
+                .init_resource::<ShipSprites>()
+                .insert_resource(SpriteInfos {
+                    player: (Handle::default(), Vec2::new(60., 30.)),
+                    player_laser: (Handle::default(), Vec2::new(9., 54.)),
+                    red_enemy: (Handle::default(), Vec2::new(40., 32.)),
+                    green_enemy: (Handle::default(), Vec2::new(40., 32.)),
+                    yellow_enemy: (Handle::default(), Vec2::new(40., 32.)),
+                    alien_laser: (Handle::default(), Vec2::new(17., 55.)),
+                    top_alien: (Handle::default(), Vec2::new(40., 20.)),
+                })
+                .insert_resource(AudioInfos {
+                    laser: Handle::default(),
+                    explosion: Handle::default(),
+                })
+                .add_plugin(ControlsPlugin)
+                .add_plugin(DifficultyPlugin)
+                .add_plugin(SaveGamePlugin)
+                .add_plugins(SharedPluginGroup)
+                .add_plugins(EntitiesPluginGroup)
+                // Whatever the bindings saved on this machine
+                .insert_resource(ControlBindings::default());
+
+            let dive_paths = ron::from_str::<DivePaths>(include_str!("../assets/data/dive_paths.dives.ron")).unwrap();
+            let dive_paths = app.world.get_resource_mut::<Assets<DivePaths>>().unwrap().add(dive_paths);
+            app.insert_resource(DataInfos {
+                dive_paths,
+                ships: Handle::default(),
+            });
+
+            for stage in [CoreStage::First, CoreStage::PreUpdate, CoreStage::Update, CoreStage::PostUpdate, CoreStage::Last] {
+                app.stage(stage, |stage: &mut SystemStage| {
+                    stage.set_executor(Box::new(SingleThreadedExecutor::default()));
+                    stage
+                });
+            }
+
+            Self { app, frame_time: frame_time.clone() }
+        }
+
+        fn run(&mut self, frames: u32) {
+            for _ in 0..frames {
+                self.app.insert_resource(self.frame_time.clone());
+                self.app.update();
+            }
+        }
+
+        fn state(&mut self) -> Mut<'_, State<GameState>> {
+            self.app.world.get_resource_mut::<State<GameState>>().unwrap()
+        }
+
+        fn press(&mut self, key: KeyCode) {
+            self.app.world.get_resource_mut::<Input<KeyCode>>().unwrap().press(key);
+        }
+
+        fn snapshot(&mut self) -> SaveGame {
+            let mut state = SystemState::<GameSnapshot>::new(&mut self.app.world);
+            let save = state.get_mut(&mut self.app.world).take().unwrap();
+            state.apply(&mut self.app.world);
+            save
+        }
+    }
+
+    fn to_ron<T: Serialize>(value: &T) -> String {
+        ron::to_string(value).unwrap()
+    }
+
+    /// Everything in a save, one line per thing on the board, sorted so
+    /// the order entities were spawned in doesn't matter
+    fn fingerprint(mut save: SaveGame) -> Vec<String> {
+        let mut boss_parts = save.board.boss
+            .as_mut()
+            .map(|boss| std::mem::take(&mut boss.parts))
+            .unwrap_or_default();
+        let enemies = save.board.aliens
+            .iter()
+            .map(to_ron)
+            .chain(boss_parts.iter().map(to_ron))
+            .collect::<Vec<_>>();
+
+        let mut lines = Vec::new();
+        lines.extend(enemies.iter().map(|enemy| format!("enemy {}", enemy)));
+        lines.extend(save.board.obstacles.drain(..).map(|block| format!("block {}", to_ron(&block))));
+        lines.extend(save.power_ups.drain(..).map(|power_up| format!("power-up {}", to_ron(&power_up))));
+        for mut projectile in save.projectiles.drain(..) {
+            let pierced = std::mem::take(&mut projectile.pierced)
+                .into_iter()
+                .map(|index| enemies[index].clone())
+                .collect::<Vec<_>>();
+            lines.push(format!("projectile {} through {:?}", to_ron(&projectile), pierced));
+        }
+        boss_parts.clear();
+        save.board.aliens.clear();
+        lines.push(format!("game {}", to_ron(&save)));
+
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn loaded_game_plays_out_like_the_saved_one() {
+        let frame_time = frame_time();
+        let mut saved = TestGame::new(&frame_time);
+        saved.app.world.get_resource_mut::<DiveAttacks>().unwrap().enabled = true;
+        saved.app.world.get_resource_mut::<DifficultyDirector>().unwrap().enabled = true;
+        saved.state().set(GameState::InGame).unwrap();
+        saved.press(KeyCode::Space);
+        saved.press(KeyCode::LShift);
+        saved.run(1);
+
+        // Tough enough to last until the save, holding a timed power-up
+        for mut health in saved.app.world.query_filtered::<&mut Health, With<Player>>().iter_mut(&mut saved.app.world) {
+            *health = Health::from(50);
+        }
+        let mut state = SystemState::<Commands>::new(&mut saved.app.world);
+        spawn_power_up(&mut state.get_mut(&mut saved.app.world), PowerUpKind::RapidFire, Vec3::new(0., -200., 4.));
+        state.apply(&mut saved.app.world);
+        // The clock runs at real frame lengths, so wait for the dive rather
+        // than count on it
+        let mut frames = 1;
+        while saved.app.world.query::<&Diving>().iter(&saved.app.world).next().is_none() {
+            assert!(frames < MAX_FRAMES_BEFORE_DIVE, "nobody dives before the save");
+            saved.run(1);
+            frames += 1;
+        }
+        saved.run(FRAMES_INTO_DIVE);
+
+        // Saved from the pause menu like in the game
+        saved.state().push(GameState::Paused).unwrap();
+        saved.run(2);
+        let save = saved.snapshot();
+        assert!(save.board.aliens.iter().any(|alien| alien.diving.is_some()), "nobody dives when saving");
+        assert!(!save.projectiles.is_empty(), "no shots in flight when saving");
+        assert!(save.players[0].ship.as_ref().is_some_and(|ship| to_ron(&ship.modifiers).contains("RapidFire")), "no power-up held when saving");
+        let save = ron::from_str::<SaveGame>(&ron::to_string(&save).unwrap()).unwrap();
+        saved.state().pop().unwrap();
+        saved.run(FRAMES_AFTER_SAVE);
+
+        let mut loaded = TestGame::new(&frame_time);
+        loaded.app.world.get_resource_mut::<PendingLoad>().unwrap().0 = Some(save);
+        loaded.state().set(GameState::InGame).unwrap();
+        loaded.press(KeyCode::Space);
+        loaded.press(KeyCode::LShift);
+        // Loading happens on the frame after entering the game
+        loaded.run(1 + FRAMES_AFTER_SAVE);
+
+        assert_eq!(fingerprint(loaded.snapshot()), fingerprint(saved.snapshot()));
+    }
+}
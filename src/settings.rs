@@ -0,0 +1,129 @@
+use bevy::{prelude::*, window::WindowMode};
+use serde::{Deserialize, Serialize};
+
+use crate::{WINDOW_HEIGHT, WINDOW_WIDTH, config};
+
+const SETTINGS_FILE: &str = "settings.ron";
+const SETTINGS_VERSION: u32 = 1;
+/// Window sizes offered in the settings menu
+pub const WINDOW_SIZES: [(f32, f32); 3] = [(600., 600.), (800., 800.), (1000., 1000.)];
+
+/// Persisted audio and video options, key bindings live in
+/// `ControlBindings`. Keys missing from the file keep their default.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    /// Applied on the next launch
+    pub window_width: f32,
+    pub window_height: f32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    /// 0 to 1. Bevy's audio has no volume control yet, so anything above 0
+    /// plays at full volume and 0 mutes.
+    pub volume: f32,
+    pub explosions: bool,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            window_width: WINDOW_WIDTH,
+            window_height: WINDOW_HEIGHT,
+            fullscreen: false,
+            vsync: true,
+            volume: 1.,
+            explosions: true,
+        }
+    }
+}
+impl Settings {
+    /// Loads saved settings, falling back to defaults for an unknown version
+    pub fn load() -> Self {
+        let mut settings = config::load_ron::<Self>(SETTINGS_FILE)
+            .filter(|saved| saved.version == SETTINGS_VERSION)
+            .unwrap_or_default();
+
+        settings.volume = settings.volume.clamp(0., 1.);
+        if settings.window_width < WINDOW_WIDTH || settings.window_height < WINDOW_HEIGHT {
+            settings.window_width = WINDOW_WIDTH;
+            settings.window_height = WINDOW_HEIGHT;
+        }
+        settings
+    }
+
+    pub fn save(&self) {
+        config::save_ron(SETTINGS_FILE, self);
+    }
+
+    pub fn sound_on(&self) -> bool {
+        self.volume > 0.
+    }
+
+    /// Mutes, or unmutes back to full volume
+    pub fn toggle_sound(&mut self) {
+        self.volume = if self.sound_on() { 0. } else { 1. };
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        if self.fullscreen { WindowMode::BorderlessFullscreen } else { WindowMode::Windowed }
+    }
+
+    pub fn window_descriptor(&self) -> WindowDescriptor {
+        WindowDescriptor {
+            title: "".to_string(),
+            width: self.window_width,
+            height: self.window_height,
+            vsync: self.vsync,
+            mode: self.window_mode(),
+            ..Default::default()
+        }
+    }
+
+    /// Next of the offered window sizes, wrapping around
+    pub fn next_window_size(&mut self) {
+        let idx = WINDOW_SIZES
+            .iter()
+            .position(|&(w, h)| w == self.window_width && h == self.window_height)
+            .map(|idx| (idx + 1) % WINDOW_SIZES.len())
+            .unwrap_or(0);
+        let (w, h) = WINDOW_SIZES[idx];
+        self.window_width = w;
+        self.window_height = h;
+    }
+}
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system(apply_settings)
+            .add_system(save_settings)
+        ;
+    }
+}
+
+/// Pushes the video options to the window whenever they change
+fn apply_settings(
+    settings: Res<Settings>,
+    mut windows: ResMut<Windows>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    if let Some(window) = windows.get_primary_mut() {
+        if window.mode() != settings.window_mode() {
+            window.set_mode(settings.window_mode());
+        }
+        if window.vsync() != settings.vsync {
+            window.set_vsync(settings.vsync);
+        }
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    if settings.is_changed() && !settings.is_added() {
+        settings.save();
+    }
+}
@@ -7,7 +7,8 @@ pub use self::weapons::*;
 pub use self::attributes::*;
 pub use self::physics::*;
 
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct RenderedAssetInfo {
     pub size: Vec2,
 }
@@ -1,11 +1,16 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component)]
+use super::RenderedAssetInfo;
+
+#[derive(Component, Reflect, Clone, Debug, Default, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct MovementSpeed {
     pub value: f32,
 }
 
-#[derive(Component)]
+#[derive(Component, Reflect, Clone, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct Health {
     pub original_hp: u32,
     pub current_hp: u32,
@@ -27,7 +32,21 @@ impl Health {
     }
 }
 
+/// Points awarded to the player who destroys this entity
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct ScoreValue {
+    pub points: u32,
+}
+
 pub struct AttributesPlugin;
 impl Plugin for AttributesPlugin {
-    fn build(&self, app: &mut App) {}
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<MovementSpeed>()
+            .register_type::<Health>()
+            .register_type::<ScoreValue>()
+            .register_type::<RenderedAssetInfo>()
+        ;
+    }
 }
@@ -1,10 +1,14 @@
+use std::f32::consts::PI;
+
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::{WinSize, GAME_TIME_STEP};
+use crate::{GameState, WinSize, GAME_TIME_STEP, entities::{Enemy, FromPlayer, Player}};
 
-use super::Projectile;
+use super::{Homing, Projectile};
 
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct Velocity {
     pub value: Vec2,
 }
@@ -16,27 +20,133 @@ impl Velocity {
     }
 }
 
+/// Sideways pattern layered on top of a projectile's `Velocity`
+#[derive(Clone, Copy, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect_value(PartialEq, Serialize, Deserialize)]
+pub enum MotionPattern {
+    Linear,
+    /// Smooth sway either side of the straight path
+    Sine { amplitude: f32, frequency: f32 },
+    /// Sharp side to side steps, one leg every `interval` seconds
+    ZigZag { amplitude: f32, interval: f32 },
+}
+impl MotionPattern {
+    /// Sideways distance from the straight path after `t` seconds
+    fn offset(&self, t: f32) -> f32 {
+        match *self {
+            MotionPattern::Linear => 0.,
+            MotionPattern::Sine { amplitude, frequency } => {
+                amplitude * (2. * PI * frequency * t).sin()
+            }
+            MotionPattern::ZigZag { amplitude, interval } => {
+                // Triangle wave between -amplitude and amplitude
+                let phase = (t / interval).rem_euclid(2.);
+                let tri = if phase < 1. { phase } else { 2. - phase };
+                amplitude * (2. * tri - 1.)
+            }
+        }
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Motion {
+    pub pattern: MotionPattern,
+    pub elapsed: f32,
+}
+impl Default for Motion {
+    fn default() -> Self {
+        Self::new(MotionPattern::Linear)
+    }
+}
+impl Motion {
+    pub fn new(pattern: MotionPattern) -> Self {
+        Self { pattern, elapsed: 0. }
+    }
+}
+
 pub struct PhysicsPlugin;
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_system(move_all_projectiles)
+            .register_type::<Velocity>()
+            .register_type::<Motion>()
+            .register_type::<MotionPattern>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(
+                        steer_homing_projectiles
+                            .label("movement")
+                            .after("player_shooting")
+                            .before("move_projectiles")
+                    )
+                    .with_system(
+                        move_all_projectiles
+                            .label("move_projectiles")
+                            .label("movement")
+                            .after("player_shooting")
+                    )
+            )
         ;
     }
 }
 
 fn move_all_projectiles(
     mut commands: Commands,
-    mut q: Query<(Entity, &Velocity, &mut Transform), With<Projectile>>,
+    mut q: Query<(Entity, &Velocity, Option<&mut Motion>, &mut Transform), With<Projectile>>,
     win_size: Res<WinSize>,
 ) {
-    for (entity, vel, mut tf) in q.iter_mut() {
+    for (entity, vel, motion, mut tf) in q.iter_mut() {
         tf.translation.y += vel.value.y * GAME_TIME_STEP;
         tf.translation.x += vel.value.x * GAME_TIME_STEP;
 
+        if let Some(mut motion) = motion {
+            let before = motion.pattern.offset(motion.elapsed);
+            motion.elapsed += GAME_TIME_STEP;
+            let after = motion.pattern.offset(motion.elapsed);
+
+            // Sideways is perpendicular to the direction of travel
+            let side = vel.value.normalize_or_zero().perp();
+            tf.translation += (side * (after - before)).extend(0.);
+        }
+
         if  tf.translation.y.abs() > win_size.h || 
             tf.translation.x.abs() > win_size.w {
                 commands.entity(entity).despawn();
         }
     }
-}
\ No newline at end of file
+}
+
+/// Turns homing projectiles toward the nearest target, player missiles
+/// chase aliens and alien missiles chase players.
+fn steer_homing_projectiles(
+    mut q: Query<(&Homing, &mut Velocity, &mut Transform, Option<&FromPlayer>), With<Projectile>>,
+    enemy_q: Query<&GlobalTransform, (With<Enemy>, Without<Projectile>)>,
+    player_q: Query<&Transform, (With<Player>, Without<Projectile>)>,
+) {
+    for (homing, mut vel, mut tf, from_player) in q.iter_mut() {
+        let pos = tf.translation.truncate();
+        let targets: Vec<Vec2> = match from_player {
+            Some(_) => enemy_q.iter().map(|t| t.translation.truncate()).collect(),
+            None => player_q.iter().map(|t| t.translation.truncate()).collect(),
+        };
+        let target = targets
+            .into_iter()
+            .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)));
+
+        if let Some(target) = target {
+            let current = vel.value.y.atan2(vel.value.x);
+            let desired = (target - pos).y.atan2((target - pos).x);
+
+            // Shortest signed angle between the two headings
+            let mut diff = desired - current;
+            while diff > PI { diff -= 2. * PI; }
+            while diff < -PI { diff += 2. * PI; }
+
+            let max_turn = homing.turn_rate * GAME_TIME_STEP;
+            let heading = current + diff.clamp(-max_turn, max_turn);
+            vel.value = Vec2::new(heading.cos(), heading.sin()) * vel.value.length();
+            tf.rotation = Quat::from_rotation_z((-vel.value.x).atan2(vel.value.y));
+        }
+    }
+}
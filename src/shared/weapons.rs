@@ -1,56 +1,277 @@
 use std::collections::HashSet;
 
-use bevy::{prelude::*, sprite::collide_aabb::{Collision, collide}, reflect::List};
-use crate::{Game, entities::{FromPlayer, Enemy, Obstacle, FromEnemy, Player}, SpriteInfos, AssetScaling};
-use super::{Health, RenderedAssetInfo};
+use bevy::{prelude::*, sprite::collide_aabb::collide};
+use serde::{Deserialize, Serialize};
+use crate::{Game, GameState, AudioInfos, settings::Settings, entities::{Ability, AlienType, FromPlayer, Enemy, EnemyDestroyed, Obstacle, FromEnemy, Player, Players}};
+use super::{Health, RenderedAssetInfo, ScoreValue, Velocity};
 
-#[derive(Component)]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct Projectile {
-    damage: u32
+    pub damage: u32,
+    /// Targets this projectile can still pass through
+    pub pierce: u32,
+    /// Targets already passed through, so they aren't hit again next frame
+    pub pierced: Vec<Entity>,
+    /// Decides which shot survives when two opposing shots meet
+    pub strength: u32,
 }
 impl Default for Projectile {
     fn default() -> Self {
-        Self { damage: 1 }
+        Self {
+            damage: 1,
+            pierce: 0,
+            pierced: Vec::new(),
+            strength: 1,
+        }
     }
 }
 
+/// Short lived flash left where two shots cancelled each other or an alien
+/// was shot down
 #[derive(Component)]
+pub struct Explosion {
+    timer: Timer,
+}
+
+const EXPLOSION_SIZE: f32 = 10.;
+const EXPLOSION_DURATION: f32 = 0.2;
+
+/// Steers a projectile toward the closest target of the opposing side
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Homing {
+    /// Radians per second
+    pub turn_rate: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect_value(PartialEq, Serialize, Deserialize)]
+pub enum WeaponKind {
+    Normal,
+    Spread,
+    PiercingLaser,
+    RapidFire,
+    Charged,
+    HomingMissile,
+}
+
+/// Stats of a weapon archetype
+#[derive(Clone, Debug, Reflect, Serialize, Deserialize)]
+pub struct Weapon {
+    pub kind: WeaponKind,
+    pub projectile_count: u32,
+    /// Angle in radians covered by the fan of projectiles
+    pub spread: f32,
+    pub projectile_speed: f32,
+    pub damage: u32,
+    pub pierce: u32,
+    pub cooldown: f64,
+    /// Seconds of holding fire for a full charge, 0 fires on press
+    pub charge_time: f32,
+    /// Damage added on top of `damage` by a full charge
+    pub charge_damage: u32,
+    /// Radians per second, 0 flies straight
+    pub homing_turn_rate: f32,
+    /// Strength of the projectiles against opposing shots
+    pub strength: u32,
+}
+impl Weapon {
+    pub fn new(kind: WeaponKind) -> Self {
+        let normal = Self {
+            kind,
+            projectile_count: 1,
+            spread: 0.,
+            projectile_speed: 250.,
+            damage: 1,
+            pierce: 0,
+            cooldown: 0.8,
+            charge_time: 0.,
+            charge_damage: 0,
+            homing_turn_rate: 0.,
+            strength: 1,
+        };
+
+        match kind {
+            WeaponKind::Normal => normal,
+            WeaponKind::Spread => Self {
+                projectile_count: 3,
+                spread: 0.5,
+                projectile_speed: 230.,
+                cooldown: 1.,
+                ..normal
+            },
+            WeaponKind::PiercingLaser => Self {
+                projectile_speed: 400.,
+                pierce: 3,
+                cooldown: 1.2,
+                ..normal
+            },
+            WeaponKind::RapidFire => Self {
+                projectile_speed: 300.,
+                cooldown: 0.25,
+                ..normal
+            },
+            WeaponKind::Charged => Self {
+                projectile_speed: 300.,
+                cooldown: 0.5,
+                charge_time: 1.,
+                charge_damage: 3,
+                ..normal
+            },
+            WeaponKind::HomingMissile => Self {
+                projectile_speed: 180.,
+                damage: 2,
+                cooldown: 1.5,
+                homing_turn_rate: 3.,
+                ..normal
+            },
+        }
+    }
+}
+
+#[derive(Component, Reflect, Clone, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct WeaponState {
     pub ready: bool,
-    pub cooldown: f64,
-    pub last_fired: f64,
-    pub projectile_speed: f32,
+    /// Seconds since the last shot, `None` once the weapon is ready again
+    pub since_fired: Option<f64>,
+    pub weapon: Weapon,
+    /// Seconds fire has been held for a charged weapon
+    pub charge: Option<f32>,
+}
+impl Default for WeaponState {
+    fn default() -> Self {
+        Self::new(WeaponKind::Normal)
+    }
 }
 impl WeaponState {
-    pub fn fast_normal_weapon() -> Self {
+    pub fn new(kind: WeaponKind) -> Self {
         Self {
             ready: true,
-            cooldown: 0.8,
-            last_fired: 0.,
-            projectile_speed: 250.,
-            // projectile_speed: 100.,
+            since_fired: None,
+            weapon: Weapon::new(kind),
+            charge: None,
         }
     }
 
-    pub fn fired(&mut self, time: f64) {
+    pub fn fast_normal_weapon() -> Self {
+        Self::new(WeaponKind::Normal)
+    }
+
+    pub fn fired(&mut self) {
         self.ready = false;
-        self.last_fired = time;
+        self.since_fired = Some(0.);
+        self.charge = None;
     }
 
     pub fn reset(&mut self) {
         self.ready = true;
-        self.last_fired = 0.;
+        self.since_fired = None;
     }
 }
 
+/// Spawns the projectiles of one shot of `weapon`, fanned out around
+/// `direction` (1 shoots up, -1 down). `charge` is the fraction of a full
+/// charge for charged weapons. Returns the projectiles so the caller can
+/// tag which side fired them.
+pub fn fire_weapon(
+    commands: &mut Commands,
+    weapon: &Weapon,
+    origin: Vec3,
+    direction: f32,
+    sprite: &(Handle<Image>, Vec2),
+    scale: Vec3,
+    charge: f32,
+) -> Vec<Entity> {
+    let asset_size = scale.truncate() * sprite.1;
+    let damage = weapon.damage + (weapon.charge_damage as f32 * charge.clamp(0., 1.)).round() as u32;
+    let count = weapon.projectile_count.max(1);
+
+    (0..count)
+        .map(|idx| {
+            let angle = if count == 1 {
+                0.
+            } else {
+                -weapon.spread / 2. + idx as f32 * weapon.spread / (count - 1) as f32
+            };
+            let velocity = Vec2::new(angle.sin(), direction * angle.cos()) * weapon.projectile_speed;
+
+            let mut projectile = commands.spawn();
+            projectile
+                .insert_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(asset_size),
+                        ..Default::default()
+                    },
+                    texture: sprite.0.clone(),
+                    transform: Transform {
+                        // Sprites point up, turn them to face where they fly
+                        rotation: Quat::from_rotation_z((-velocity.x).atan2(velocity.y)),
+                        translation: Vec3::new(origin.x, origin.y, 0.),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(RenderedAssetInfo::new(asset_size))
+                .insert(Projectile {
+                    damage,
+                    pierce: weapon.pierce,
+                    pierced: Vec::new(),
+                    strength: weapon.strength,
+                })
+                .insert(Velocity { value: velocity });
+
+            if weapon.homing_turn_rate > 0. {
+                projectile.insert(Homing { turn_rate: weapon.homing_turn_rate });
+            }
+            projectile.id()
+        })
+        .collect()
+}
+
+pub fn spawn_explosion(commands: &mut Commands, position: Vec3) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(1., 0.9, 0.6),
+                custom_size: Some(Vec2::new(EXPLOSION_SIZE, EXPLOSION_SIZE)),
+                ..Default::default()
+            },
+            transform: Transform {
+                translation: Vec3::new(position.x, position.y, 6.),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(Explosion {
+            timer: Timer::from_seconds(EXPLOSION_DURATION, false),
+        });
+}
+
 pub struct WeaponsPlugin;
 impl Plugin for WeaponsPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_system(manage_all_weapons_state)
-            .add_system(manage_player_projectiles_hit_enemies)
-            .add_system(manage_projectiles_hit_obstacles)
-            .add_system(manage_enemy_projectiles_hit_player)
+            .register_type::<Projectile>()
+            .register_type::<WeaponState>()
+            .register_type::<Weapon>()
+            .register_type::<WeaponKind>()
+            .register_type::<Homing>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(manage_all_weapons_state.label("weapons_state"))
+                    .with_system(manage_player_projectiles_hit_enemies.label("collisions").after("movement"))
+                    .with_system(manage_projectiles_hit_obstacles.label("collisions").after("movement"))
+                    .with_system(
+                        manage_enemy_projectiles_hit_player
+                            .label("collisions")
+                            .label("projectiles_hit_player")
+                            .after("movement")
+                    )
+                    .with_system(manage_projectiles_hit_projectiles.label("collisions").after("movement"))
+                    .with_system(fade_explosions)
+            )
         ;
     }
 }
@@ -61,27 +282,31 @@ fn manage_all_weapons_state (
 ) {
     // might have to check if it is online?
     for mut w_state in q.iter_mut() {
-        let now = time.seconds_since_startup();
-        let last_shot = w_state.last_fired;
-
-        if w_state.last_fired == 0. || now > last_shot + w_state.cooldown {
-            w_state.reset();
+        // Counted in game time rather than since startup, so it can be saved
+        match w_state.since_fired.map(|secs| secs + time.delta_seconds_f64()) {
+            Some(secs) if secs <= w_state.weapon.cooldown => w_state.since_fired = Some(secs),
+            _ => w_state.reset(),
         }
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn manage_enemy_projectiles_hit_player (
     mut commands: Commands,
-    mut game: ResMut<Game>,
     projectile_q: Query<
         (Entity, &Projectile, &RenderedAssetInfo, &Transform), 
         (With<FromEnemy>, With<Projectile>)
     >, // projectiles
-    mut player_q: Query<(Entity, &mut Health, &RenderedAssetInfo, &Transform), With<Player>>,
+    mut player_q: Query<(Entity, &mut Health, Option<&Ability>, &RenderedAssetInfo, &Transform), With<Player>>,
 ) {
     let mut entities_despawned: HashSet<Entity> = HashSet::new();
-    if let Ok((p_en, mut p_hp, p_rai, p_tf)) = player_q.get_single_mut() {
+    for (p_en, mut p_hp, ability, p_rai, p_tf) in player_q.iter_mut() {
+        let shielded = ability.map(|ability| ability.absorbs_shots()).unwrap_or(false);
         for (proj_en, proj, proj_asset_info, proj_tf) in projectile_q.iter() {
+            if entities_despawned.contains(&proj_en) || p_hp.current_hp == 0 {
+                continue;
+            }
+
             let collision = collide(
                 proj_tf.translation,
                 proj_asset_info.size,
@@ -89,11 +314,15 @@ fn manage_enemy_projectiles_hit_player (
                 p_rai.size,
             );
 
-            if let Some(_) = collision {
-                p_hp.current_hp -= proj.damage;
+            if collision.is_some() {
                 commands.entity(proj_en).despawn();
+                entities_despawned.insert(proj_en);
+                if shielded {
+                    continue;
+                }
 
-                if  p_hp.current_hp <= 0 {
+                p_hp.current_hp = p_hp.current_hp.saturating_sub(proj.damage);
+                if  p_hp.current_hp == 0 {
                     commands.entity(p_en).despawn();
                 }
             }
@@ -101,18 +330,29 @@ fn manage_enemy_projectiles_hit_player (
     }
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn manage_player_projectiles_hit_enemies (
     mut commands: Commands,
     mut game: ResMut<Game>,
-    projectile_q: Query<
-        (Entity, &Projectile, &RenderedAssetInfo, &Transform), 
-        (With<FromPlayer>, With<Projectile>)
+    mut players: ResMut<Players>,
+    mut destroyed_events: EventWriter<EnemyDestroyed>,
+    mut projectile_q: Query<
+        (Entity, &mut Projectile, &FromPlayer, &RenderedAssetInfo, &Transform), 
+        With<Projectile>
     >, // projectiles
-    mut enemy_q: Query<(Entity, &mut Health, &RenderedAssetInfo, &Transform), With<Enemy>>,
+    mut enemy_q: Query<(Entity, &mut Health, &ScoreValue, Option<&AlienType>, &RenderedAssetInfo, &GlobalTransform, Option<&Parent>), With<Enemy>>,
+    audio_infos: Res<AudioInfos>,
+    audio: Res<Audio>,
+    settings: Res<Settings>,
 ) {
     let mut entities_despawned: HashSet<Entity> = HashSet::new();
-    for (proj_en, proj, proj_asset_info, proj_tf) in projectile_q.iter() {
-        for (ene_en, mut ene_health, enemy_asset_info, ene_tf) in enemy_q.iter_mut() {
+    for (proj_en, mut proj, from, proj_asset_info, proj_tf) in projectile_q.iter_mut() {
+        for (ene_en, mut ene_health, ene_score, alien_type, enemy_asset_info, ene_tf, formation) in enemy_q.iter_mut() {
+            if  entities_despawned.contains(&proj_en) ||
+                entities_despawned.contains(&ene_en) ||
+                proj.pierced.contains(&ene_en) {
+                continue;
+            }
 
             let collision = collide(
                 proj_tf.translation,
@@ -121,15 +361,35 @@ fn manage_player_projectiles_hit_enemies (
                 enemy_asset_info.size,
             );
 
-            if let Some(_) = collision {
-                ene_health.current_hp -= proj.damage;
-                println!("{}", ene_health.current_hp);
-                commands.entity(proj_en).despawn();
+            if collision.is_some() {
+                ene_health.current_hp = ene_health.current_hp.saturating_sub(proj.damage);
+                if proj.pierce > 0 {
+                    proj.pierce -= 1;
+                    proj.pierced.push(ene_en);
+                } else {
+                    commands.entity(proj_en).despawn();
+                    entities_despawned.insert(proj_en);
+                }
 
-                if  ene_health.current_hp <= 0 && 
-                    entities_despawned.get(&ene_en).is_none() {
-                        commands.entity(ene_en).despawn();
+                if  ene_health.current_hp == 0 {
+                        // Detached first so the formation keeps no stale child
+                        if let Some(formation) = formation {
+                            commands.entity(formation.0).remove_children(&[ene_en]);
+                        }
+                        commands.entity(ene_en).despawn_recursive();
+                        entities_despawned.insert(ene_en);
                         game.active_enemies -= 1;
+                        if settings.sound_on() {
+                            audio.play(audio_infos.explosion.clone());
+                        }
+                        destroyed_events.send(EnemyDestroyed {
+                            position: ene_tf.translation,
+                            alien_type: alien_type.copied(),
+                        });
+
+                        if let Some(slot) = players.slots.get_mut(from.player) {
+                            slot.score += ene_score.points;
+                        }
                 }
             }
         }
@@ -159,21 +419,75 @@ fn manage_projectiles_hit_obstacles (
                 ob_rai.size,
             );
 
-            if let Some(_) = collision {
-                ob_health.current_hp -= proj.damage;
+            if collision.is_some() {
+                ob_health.current_hp = ob_health.current_hp.saturating_sub(proj.damage);
 
                 // Despawn and ensure entity is not despawned twice
-                if (entities_despawned.get(&proj_en)).is_none() {
+                if !entities_despawned.contains(&proj_en) {
                     commands.entity(proj_en).despawn();
                     entities_despawned.insert(proj_en);
                 }
 
-                if  ob_health.current_hp <= 0 && 
-                    entities_despawned.get(&ob_en).is_none() {
+                if  ob_health.current_hp == 0 && 
+                    !entities_despawned.contains(&ob_en) {
                         commands.entity(ob_en).despawn();
                         entities_despawned.insert(ob_en);
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
+/// Opposing shots that meet cancel out: the weaker one is destroyed, and
+/// both when they are as strong as each other.
+fn manage_projectiles_hit_projectiles (
+    mut commands: Commands,
+    player_proj_q: Query<(Entity, &Projectile, &RenderedAssetInfo, &Transform), With<FromPlayer>>,
+    enemy_proj_q: Query<(Entity, &Projectile, &RenderedAssetInfo, &Transform), With<FromEnemy>>,
+    settings: Res<Settings>,
+) {
+    let mut entities_despawned: HashSet<Entity> = HashSet::new();
+    for (pl_en, pl_proj, pl_rai, pl_tf) in player_proj_q.iter() {
+        for (en_en, en_proj, en_rai, en_tf) in enemy_proj_q.iter() {
+            if  entities_despawned.contains(&pl_en) ||
+                entities_despawned.contains(&en_en) {
+                continue;
+            }
+
+            let collision = collide(
+                pl_tf.translation,
+                pl_rai.size,
+                en_tf.translation,
+                en_rai.size,
+            );
+
+            if collision.is_some() {
+                if pl_proj.strength <= en_proj.strength {
+                    commands.entity(pl_en).despawn();
+                    entities_despawned.insert(pl_en);
+                }
+                if en_proj.strength <= pl_proj.strength {
+                    commands.entity(en_en).despawn();
+                    entities_despawned.insert(en_en);
+                }
+                if settings.explosions {
+                    spawn_explosion(&mut commands, (pl_tf.translation + en_tf.translation) / 2.);
+                }
+            }
+        }
+    }
+}
+
+fn fade_explosions(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q: Query<(Entity, &mut Explosion, &mut Sprite)>,
+) {
+    for (entity, mut explosion, mut sprite) in q.iter_mut() {
+        explosion.timer.tick(time.delta());
+        if explosion.timer.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_a(1. - explosion.timer.percent());
+        }
+    }
+}
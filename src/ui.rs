@@ -0,0 +1,163 @@
+mod game_over;
+mod hud;
+mod main_menu;
+mod pause;
+mod rebind;
+mod settings_menu;
+mod ship_select;
+
+use bevy::{app::PluginGroupBuilder, prelude::*};
+pub use self::game_over::*;
+pub use self::hud::*;
+pub use self::main_menu::*;
+pub use self::pause::*;
+pub use self::rebind::*;
+pub use self::settings_menu::*;
+pub use self::ship_select::*;
+
+use crate::controls::{Action, ActionState};
+
+const MENU_TITLE_FONT_SIZE: f32 = 28.;
+const MENU_FONT_SIZE: f32 = 14.;
+const MENU_TEXT_COLOR: Color = Color::rgb(0.8, 0.8, 0.8);
+const MENU_SELECTED_COLOR: Color = Color::rgb(0.95, 0.31, 0.31);
+const MENU_BACKGROUND_COLOR: Color = Color::rgba(0.04, 0.04, 0.04, 0.85);
+
+/// Highlighted entry of whichever menu is currently open
+#[derive(Default)]
+pub struct MenuCursor {
+    pub selected: usize,
+    pub len: usize,
+}
+impl MenuCursor {
+    pub fn reset(&mut self, len: usize) {
+        self.selected = 0;
+        self.len = len;
+    }
+
+    /// Moves the cursor with the movement actions, wrapping at both ends
+    pub fn navigate(&mut self, actions: &ActionState) {
+        if self.len == 0 {
+            return;
+        }
+        if actions.just_pressed(Action::MoveUp) || actions.just_pressed(Action::MoveLeft) {
+            self.selected = (self.selected + self.len - 1) % self.len;
+        } else if actions.just_pressed(Action::MoveDown) || actions.just_pressed(Action::MoveRight) {
+            self.selected = (self.selected + 1) % self.len;
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct MenuItem {
+    pub index: usize,
+}
+
+/// Spawns a full screen menu with a title and one text entry per item.
+/// `marker` is put on the root so the menu can be despawned as a whole.
+pub fn spawn_menu(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    title: &str,
+    items: &[String],
+    marker: impl Component,
+) -> Entity {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: UiColor(MENU_BACKGROUND_COLOR),
+            ..Default::default()
+        })
+        .insert(marker)
+        .with_children(|parent| {
+            parent.spawn_bundle(menu_text(title, font, MENU_TITLE_FONT_SIZE, Color::WHITE));
+
+            for (index, item) in items.iter().enumerate() {
+                parent
+                    .spawn_bundle(menu_text(item, font, MENU_FONT_SIZE, MENU_TEXT_COLOR))
+                    .insert(MenuItem { index });
+            }
+        })
+        .id()
+}
+
+fn menu_text(value: &str, font: &Handle<Font>, font_size: f32, color: Color) -> TextBundle {
+    TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(6.)),
+            ..Default::default()
+        },
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    }
+}
+
+pub fn despawn_with<T: Component>(
+    mut commands: Commands,
+    q: Query<Entity, With<T>>,
+) {
+    for entity in q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub struct UiPluginGroup;
+impl PluginGroup for UiPluginGroup {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        group
+            .add(MenuPlugin)
+            .add(MainMenuPlugin)
+            .add(ShipSelectPlugin)
+            .add(HudPlugin)
+            .add(PausePlugin)
+            .add(GameOverPlugin)
+            .add(SettingsMenuPlugin)
+            .add(RebindPlugin)
+        ;
+    }
+}
+
+pub struct MenuPlugin;
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<MenuCursor>()
+            .add_system(highlight_menu_items)
+        ;
+    }
+}
+
+fn highlight_menu_items(
+    cursor: Res<MenuCursor>,
+    mut q: Query<(&MenuItem, &mut Text)>,
+) {
+    for (item, mut text) in q.iter_mut() {
+        let color = if item.index == cursor.selected {
+            MENU_SELECTED_COLOR
+        } else {
+            MENU_TEXT_COLOR
+        };
+        // Only touch the text when needed so it isn't re-laid out every frame
+        if text.sections.iter().all(|section| section.style.color == color) {
+            continue;
+        }
+        for section in text.sections.iter_mut() {
+            section.style.color = color;
+        }
+    }
+}
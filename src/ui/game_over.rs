@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+
+use crate::{Game, GameState, FontInfos, controls::{Action, ActionState}, entities::Players, high_scores::{HighScores, NAME_LEN}};
+use super::{MenuCursor, MenuItem, spawn_menu, despawn_with};
+
+const NAME_LETTERS: &[u8; 26] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+#[derive(Component)]
+struct GameOverMenu;
+
+/// Arcade style initials entry for every player who made the table
+#[derive(Default)]
+struct NameEntry {
+    /// Players still to enter their name, best score first
+    pending: Vec<usize>,
+    /// Indices into `NAME_LETTERS`
+    letters: [usize; NAME_LEN],
+    position: usize,
+}
+impl NameEntry {
+    fn name(&self) -> String {
+        self.letters.iter().map(|&idx| NAME_LETTERS[idx] as char).collect()
+    }
+
+    /// Initials with the letter being edited bracketed
+    fn label(&self) -> String {
+        self.letters
+            .iter()
+            .enumerate()
+            .map(|(position, &idx)| {
+                let letter = NAME_LETTERS[idx] as char;
+                if position == self.position { format!("[{}]", letter) } else { format!(" {} ", letter) }
+            })
+            .collect()
+    }
+}
+
+pub struct GameOverPlugin;
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<NameEntry>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(setup_game_over_menu)
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver)
+                    .with_system(game_over_input)
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::GameOver)
+                    .with_system(despawn_with::<GameOverMenu>)
+            )
+        ;
+    }
+}
+
+fn setup_game_over_menu(
+    mut commands: Commands,
+    mut cursor: ResMut<MenuCursor>,
+    mut entry: ResMut<NameEntry>,
+    font_infos: Res<FontInfos>,
+    players: Res<Players>,
+    high_scores: Res<HighScores>,
+) {
+    // Scores are entered best first so a lower one can't push out a better one
+    let mut pending = (0..players.slots.len())
+        .filter(|&index| high_scores.qualifies(players.slots[index].score))
+        .collect::<Vec<_>>();
+    pending.sort_by(|&a, &b| players.slots[b].score.cmp(&players.slots[a].score));
+    *entry = NameEntry { pending, ..Default::default() };
+
+    spawn_next_menu(&mut commands, &mut cursor, &font_infos.main, &entry, &players);
+}
+
+/// Name entry for the next pending player, the final scores once done
+fn spawn_next_menu(
+    commands: &mut Commands,
+    cursor: &mut MenuCursor,
+    font: &Handle<Font>,
+    entry: &NameEntry,
+    players: &Players,
+) {
+    match entry.pending.first() {
+        Some(&index) => {
+            let slot = &players.slots[index];
+            let items = vec![
+                format!("{}  {:05}", slot.name, slot.score),
+                entry.label(),
+            ];
+            spawn_menu(commands, font, "NEW HIGH SCORE", &items, GameOverMenu);
+            cursor.reset(items.len());
+            cursor.selected = 1;
+        }
+        None => {
+            // Final scores followed by the only selectable entry
+            let mut items = players
+                .slots
+                .iter()
+                .map(|slot| format!("{}  {:05}", slot.name, slot.score))
+                .collect::<Vec<_>>();
+            items.push("Main menu".to_string());
+
+            spawn_menu(commands, font, "GAME OVER", &items, GameOverMenu);
+            cursor.reset(items.len());
+            cursor.selected = items.len() - 1;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn game_over_input(
+    mut commands: Commands,
+    mut state: ResMut<State<GameState>>,
+    mut actions: ResMut<ActionState>,
+    mut cursor: ResMut<MenuCursor>,
+    mut entry: ResMut<NameEntry>,
+    mut high_scores: ResMut<HighScores>,
+    mut item_q: Query<(&MenuItem, &mut Text)>,
+    menu_q: Query<Entity, With<GameOverMenu>>,
+    font_infos: Res<FontInfos>,
+    players: Res<Players>,
+    game: Res<Game>,
+) {
+    let index = match entry.pending.first() {
+        Some(&index) => index,
+        None => {
+            if actions.just_pressed(Action::Confirm) {
+                actions.consume(Action::Confirm);
+                state.set(GameState::MainMenu).unwrap();
+            }
+            return;
+        }
+    };
+
+    // Up and down cycle the letter, left and right pick which one
+    let position = entry.position;
+    let letters = NAME_LETTERS.len();
+    if actions.just_pressed(Action::MoveUp) {
+        entry.letters[position] = (entry.letters[position] + 1) % letters;
+    } else if actions.just_pressed(Action::MoveDown) {
+        entry.letters[position] = (entry.letters[position] + letters - 1) % letters;
+    } else if actions.just_pressed(Action::MoveLeft) {
+        entry.position = position.saturating_sub(1);
+    } else if actions.just_pressed(Action::MoveRight) || actions.just_pressed(Action::Confirm) {
+        actions.consume(Action::Confirm);
+        entry.position = position + 1;
+    } else {
+        return;
+    }
+
+    if entry.position < NAME_LEN {
+        for (item, mut text) in item_q.iter_mut() {
+            if item.index == 1 {
+                text.sections[0].value = entry.label();
+            }
+        }
+        return;
+    }
+
+    let score = players.slots[index].score;
+    if high_scores.insert(&entry.name(), score, game.wave).is_some() {
+        high_scores.save();
+    }
+    entry.pending.remove(0);
+    entry.letters = Default::default();
+    entry.position = 0;
+
+    for entity in menu_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_next_menu(&mut commands, &mut cursor, &font_infos.main, &entry, &players);
+}
@@ -0,0 +1,257 @@
+use bevy::prelude::*;
+
+use crate::{Game, GameMode, GameState, FontInfos, entities::{Ability, Boss, BossPart, Player, PlayerState, Players}, shared::Health};
+use super::despawn_with;
+
+const HUD_FONT_SIZE: f32 = 12.;
+const HUD_MARGIN: f32 = 8.;
+const HUD_METER_WIDTH: f32 = 80.;
+const HUD_METER_READY_COLOR: Color = Color::rgb(0.4, 0.8, 1.);
+const HUD_METER_CHARGING_COLOR: Color = Color::rgb(0.4, 0.4, 0.4);
+
+#[derive(Component)]
+struct Hud;
+
+/// Score and lives readout of one player
+#[derive(Component)]
+struct HudPlayerText {
+    index: usize,
+}
+
+#[derive(Component)]
+struct HudWaveText;
+
+/// Ability cooldown of one player, filled up when the ability is ready
+#[derive(Component)]
+struct HudAbilityMeter {
+    index: usize,
+}
+
+/// Boss health bar, only shown while a boss is on the board
+#[derive(Component)]
+struct HudBossBar;
+
+#[derive(Component)]
+struct HudBossBarFill;
+
+pub struct HudPlugin;
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame)
+                    .with_system(setup_hud)
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(update_hud)
+                    .with_system(update_hud_wave)
+                    .with_system(update_hud_boss_bar)
+                    .with_system(update_hud_ability_meters)
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::MainMenu)
+                    .with_system(despawn_with::<Hud>)
+            )
+        ;
+    }
+}
+
+fn setup_hud(
+    mut commands: Commands,
+    font_infos: Res<FontInfos>,
+    players: Res<Players>,
+) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(HUD_MARGIN),
+                    left: Val::Px(HUD_MARGIN),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: font_infos.main.clone(),
+                    font_size: HUD_FONT_SIZE,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(Hud)
+        .insert(HudWaveText);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                display: Display::None,
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(HUD_MARGIN * 4.),
+                    left: Val::Percent(30.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(40.), Val::Px(HUD_MARGIN)),
+                ..Default::default()
+            },
+            color: UiColor(Color::rgb(0.25, 0.05, 0.05)),
+            ..Default::default()
+        })
+        .insert(Hud)
+        .insert(HudBossBar)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                        ..Default::default()
+                    },
+                    color: UiColor(Color::rgb(0.9, 0.15, 0.15)),
+                    ..Default::default()
+                })
+                .insert(HudBossBarFill);
+        });
+
+    for (index, slot) in players.slots.iter().enumerate() {
+        // Player 1 top left, player 2 top right
+        let position = if index == 0 {
+            Rect { top: Val::Px(HUD_MARGIN), left: Val::Px(HUD_MARGIN), ..Default::default() }
+        } else {
+            Rect { top: Val::Px(HUD_MARGIN), right: Val::Px(HUD_MARGIN), ..Default::default() }
+        };
+
+        commands
+            .spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position,
+                    ..Default::default()
+                },
+                text: Text::with_section(
+                    "",
+                    TextStyle {
+                        font: font_infos.main.clone(),
+                        font_size: HUD_FONT_SIZE,
+                        color: slot.tint,
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            })
+            .insert(Hud)
+            .insert(HudPlayerText { index });
+
+        // Meter sits under the score of its player
+        let meter_position = if index == 0 {
+            Rect { top: Val::Px(HUD_MARGIN * 4.), left: Val::Px(HUD_MARGIN), ..Default::default() }
+        } else {
+            Rect { top: Val::Px(HUD_MARGIN * 4.), right: Val::Px(HUD_MARGIN), ..Default::default() }
+        };
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: meter_position,
+                    size: Size::new(Val::Px(HUD_METER_WIDTH), Val::Px(HUD_MARGIN / 2.)),
+                    ..Default::default()
+                },
+                color: UiColor(Color::rgb(0.15, 0.15, 0.15)),
+                ..Default::default()
+            })
+            .insert(Hud)
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                            ..Default::default()
+                        },
+                        color: UiColor(HUD_METER_READY_COLOR),
+                        ..Default::default()
+                    })
+                    .insert(HudAbilityMeter { index });
+            });
+    }
+}
+
+fn update_hud(
+    players: Res<Players>,
+    mut q: Query<(&HudPlayerText, &mut Text)>,
+) {
+    if !players.is_changed() {
+        return;
+    }
+
+    for (hud_text, mut text) in q.iter_mut() {
+        if let Some(slot) = players.slots.get(hud_text.index) {
+            // Mark whose turn it is when players alternate
+            let marker = if players.mode == GameMode::Alternating && players.turn == hud_text.index {
+                "> "
+            } else {
+                ""
+            };
+            text.sections[0].value = format!(
+                "{}{}  {:05}  LIVES {}",
+                marker,
+                slot.name.to_uppercase(),
+                slot.score,
+                slot.lives,
+            );
+        }
+    }
+}
+
+fn update_hud_wave(
+    game: Res<Game>,
+    mut q: Query<&mut Text, With<HudWaveText>>,
+) {
+    if !game.is_changed() {
+        return;
+    }
+
+    for mut text in q.iter_mut() {
+        text.sections[0].value = format!("WAVE {}", game.wave);
+    }
+}
+
+fn update_hud_boss_bar(
+    boss_q: Query<&Boss>,
+    part_q: Query<&Health, With<BossPart>>,
+    mut bar_q: Query<&mut Style, (With<HudBossBar>, Without<HudBossBarFill>)>,
+    mut fill_q: Query<&mut Style, (With<HudBossBarFill>, Without<HudBossBar>)>,
+) {
+    let boss = boss_q.iter().next();
+
+    for mut style in bar_q.iter_mut() {
+        style.display = if boss.is_some() { Display::Flex } else { Display::None };
+    }
+    if let Some(boss) = boss {
+        let health_left = boss.health_left(part_q.iter());
+        for mut style in fill_q.iter_mut() {
+            style.size.width = Val::Percent(100. * health_left);
+        }
+    }
+}
+
+fn update_hud_ability_meters(
+    player_q: Query<(&PlayerState, &Ability), With<Player>>,
+    mut meter_q: Query<(&HudAbilityMeter, &mut Style, &mut UiColor)>,
+) {
+    for (meter, mut style, mut color) in meter_q.iter_mut() {
+        // Meters of players without a ship on the board read as ready
+        let charge = player_q
+            .iter()
+            .find(|(player, _)| player.index == meter.index)
+            .map(|(_, ability)| ability.charge())
+            .unwrap_or(1.);
+
+        style.size.width = Val::Percent(100. * charge);
+        color.0 = if charge >= 1. { HUD_METER_READY_COLOR } else { HUD_METER_CHARGING_COLOR };
+    }
+}
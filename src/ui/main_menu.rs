@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+
+use crate::{GameMode, GameState, FontInfos, difficulty::{Difficulty, DifficultyDirector}, controls::{Action, ActionState}, entities::{DiveAttacks, Players}, high_scores::HighScores, savegame::{PendingLoad, SaveGame}};
+use super::{MENU_TEXT_COLOR, MenuCursor, MenuItem, SettingsOrigin, menu_text, spawn_menu, despawn_with};
+
+const MAIN_MENU_MODES: [(&str, GameMode); 3] = [
+    ("1 Player", GameMode::SinglePlayer),
+    ("2 Players co-op", GameMode::Coop),
+    ("2 Players alternating", GameMode::Alternating),
+];
+
+/// Smaller than the menu so the whole table fits below it
+const HIGH_SCORE_FONT_SIZE: f32 = 10.;
+
+/// Toggle for Galaga style dives, after the game modes
+const DIVES_ITEM: usize = MAIN_MENU_MODES.len();
+/// Cycles through the difficulty presets
+const DIFFICULTY_ITEM: usize = DIVES_ITEM + 1;
+/// Toggle for the adaptive difficulty director
+const ADAPTIVE_ITEM: usize = DIFFICULTY_ITEM + 1;
+const SETTINGS_ITEM: usize = ADAPTIVE_ITEM + 1;
+/// Only listed when there is a saved game
+const CONTINUE_ITEM: usize = SETTINGS_ITEM + 1;
+
+#[derive(Component)]
+struct MainMenu;
+
+pub struct MainMenuPlugin;
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_enter(GameState::MainMenu)
+                    .with_system(setup_main_menu)
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::MainMenu)
+                    .with_system(main_menu_input)
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::MainMenu)
+                    .with_system(despawn_with::<MainMenu>)
+            )
+        ;
+    }
+}
+
+fn setup_main_menu(
+    mut commands: Commands,
+    mut cursor: ResMut<MenuCursor>,
+    font_infos: Res<FontInfos>,
+    dive_attacks: Res<DiveAttacks>,
+    difficulty: Res<Difficulty>,
+    director: Res<DifficultyDirector>,
+    high_scores: Res<HighScores>,
+) {
+    let mut items = MAIN_MENU_MODES
+        .iter()
+        .map(|(label, _)| label.to_string())
+        .chain(std::iter::once(dives_label(&dive_attacks)))
+        .chain(std::iter::once(difficulty_label(&difficulty)))
+        .chain(std::iter::once(adaptive_label(&director)))
+        .chain(std::iter::once("Settings".to_string()))
+        .collect::<Vec<_>>();
+    if SaveGame::exists() {
+        items.push("Continue saved game".to_string());
+    }
+
+    let menu = spawn_menu(&mut commands, &font_infos.main, "SPACE INVADERS", &items, MainMenu);
+    cursor.reset(items.len());
+
+    // Not menu items so the cursor skips them
+    commands.entity(menu).with_children(|parent| {
+        if !high_scores.entries.is_empty() {
+            parent.spawn_bundle(menu_text("HIGH SCORES", &font_infos.main, HIGH_SCORE_FONT_SIZE, Color::WHITE));
+        }
+        for (rank, entry) in high_scores.entries.iter().enumerate() {
+            let line = format!("{:>2}. {}  {:06}  W{}", rank + 1, entry.name, entry.score, entry.wave);
+            parent.spawn_bundle(menu_text(&line, &font_infos.main, HIGH_SCORE_FONT_SIZE, MENU_TEXT_COLOR));
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn main_menu_input(
+    mut state: ResMut<State<GameState>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut actions: ResMut<ActionState>,
+    mut game_mode: ResMut<GameMode>,
+    mut players: ResMut<Players>,
+    mut dive_attacks: ResMut<DiveAttacks>,
+    mut difficulty: ResMut<Difficulty>,
+    mut director: ResMut<DifficultyDirector>,
+    mut settings_origin: ResMut<SettingsOrigin>,
+    mut pending_load: ResMut<PendingLoad>,
+    mut item_q: Query<(&MenuItem, &mut Text)>,
+) {
+    cursor.navigate(&actions);
+    if actions.just_pressed(Action::Confirm) {
+        actions.consume(Action::Confirm);
+
+        if cursor.selected == DIVES_ITEM {
+            dive_attacks.enabled = !dive_attacks.enabled;
+            for (item, mut text) in item_q.iter_mut() {
+                if item.index == DIVES_ITEM {
+                    text.sections[0].value = dives_label(&dive_attacks);
+                }
+            }
+            return;
+        }
+
+        if cursor.selected == DIFFICULTY_ITEM {
+            *difficulty = Difficulty::from_preset(difficulty.preset.next());
+            for (item, mut text) in item_q.iter_mut() {
+                if item.index == DIFFICULTY_ITEM {
+                    text.sections[0].value = difficulty_label(&difficulty);
+                }
+            }
+            return;
+        }
+
+        if cursor.selected == ADAPTIVE_ITEM {
+            director.enabled = !director.enabled;
+            for (item, mut text) in item_q.iter_mut() {
+                if item.index == ADAPTIVE_ITEM {
+                    text.sections[0].value = adaptive_label(&director);
+                }
+            }
+            return;
+        }
+
+        if cursor.selected == SETTINGS_ITEM {
+            settings_origin.0 = GameState::MainMenu;
+            state.set(GameState::Settings).unwrap();
+            return;
+        }
+
+        if cursor.selected == CONTINUE_ITEM {
+            match SaveGame::load() {
+                Some(save) => {
+                    *game_mode = save.mode;
+                    *players = Players::new(save.mode);
+                    pending_load.0 = Some(save);
+                    state.set(GameState::InGame).unwrap();
+                }
+                None => {
+                    for (item, mut text) in item_q.iter_mut() {
+                        if item.index == CONTINUE_ITEM {
+                            text.sections[0].value = "Saved game unreadable".to_string();
+                        }
+                    }
+                }
+            }
+            return;
+        }
+
+        let (_, mode) = MAIN_MENU_MODES[cursor.selected];
+        *game_mode = mode;
+        *players = Players::new(mode);
+        state.set(GameState::ShipSelect).unwrap();
+    }
+}
+
+fn dives_label(dive_attacks: &DiveAttacks) -> String {
+    format!("Dive attacks: {}", if dive_attacks.enabled { "ON" } else { "OFF" })
+}
+
+fn difficulty_label(difficulty: &Difficulty) -> String {
+    format!("Difficulty: {}", difficulty.preset.label())
+}
+
+fn adaptive_label(director: &DifficultyDirector) -> String {
+    format!("Adaptive difficulty: {}", if director.enabled { "ON" } else { "OFF" })
+}
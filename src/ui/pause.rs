@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+
+use crate::{GameState, FontInfos, controls::{Action, ActionState}, entities::Players, savegame::{GameSaved, PendingLoad, SaveGame, SaveGameRequested}};
+use super::{MenuCursor, MenuItem, SettingsOrigin, spawn_menu, despawn_with};
+
+const PAUSE_ITEMS: [&str; 5] = ["Resume", "Save game", "Load game", "Settings", "Quit to menu"];
+const SAVE_ITEM: usize = 1;
+const LOAD_ITEM: usize = 2;
+const SETTINGS_ITEM: usize = 3;
+
+#[derive(Component)]
+struct PauseMenu;
+
+pub struct PausePlugin;
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(pause_game)
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Paused)
+                    .with_system(setup_pause_menu)
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Paused)
+                    .with_system(pause_menu_input)
+                    .with_system(show_save_result)
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Paused)
+                    .with_system(despawn_with::<PauseMenu>)
+            )
+        ;
+    }
+}
+
+fn pause_game(
+    mut state: ResMut<State<GameState>>,
+    mut actions: ResMut<ActionState>,
+) {
+    if actions.just_pressed(Action::Pause) {
+        actions.consume(Action::Pause);
+        state.push(GameState::Paused).unwrap();
+    }
+}
+
+fn setup_pause_menu(
+    mut commands: Commands,
+    mut cursor: ResMut<MenuCursor>,
+    font_infos: Res<FontInfos>,
+) {
+    let items = PAUSE_ITEMS
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>();
+
+    spawn_menu(&mut commands, &font_infos.main, "PAUSED", &items, PauseMenu);
+    cursor.reset(items.len());
+}
+
+#[allow(clippy::too_many_arguments)]
+fn pause_menu_input(
+    mut state: ResMut<State<GameState>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut actions: ResMut<ActionState>,
+    mut settings_origin: ResMut<SettingsOrigin>,
+    mut pending_load: ResMut<PendingLoad>,
+    mut save_events: EventWriter<SaveGameRequested>,
+    mut item_q: Query<(&MenuItem, &mut Text)>,
+    players: Res<Players>,
+) {
+    if actions.just_pressed(Action::Pause) {
+        actions.consume(Action::Pause);
+        state.pop().unwrap();
+        return;
+    }
+
+    cursor.navigate(&actions);
+    if actions.just_pressed(Action::Confirm) {
+        actions.consume(Action::Confirm);
+        match cursor.selected {
+            0 => state.pop().unwrap(),
+            SAVE_ITEM => {
+                save_events.send(SaveGameRequested);
+                set_item_label(&mut item_q, SAVE_ITEM, "Saving...");
+            }
+            // The HUD is laid out for the players in play, so only a save
+            // of the same mode loads in place
+            LOAD_ITEM => match SaveGame::load() {
+                Some(save) if save.mode == players.mode => {
+                    pending_load.0 = Some(save);
+                    state.pop().unwrap();
+                }
+                Some(_) => set_item_label(&mut item_q, LOAD_ITEM, "Saved game is another mode"),
+                None => set_item_label(&mut item_q, LOAD_ITEM, "No saved game"),
+            },
+            SETTINGS_ITEM => {
+                settings_origin.0 = GameState::Paused;
+                state.set(GameState::Settings).unwrap();
+            }
+            _ => state.replace(GameState::MainMenu).unwrap(),
+        }
+    }
+}
+
+fn show_save_result(
+    mut events: EventReader<GameSaved>,
+    mut item_q: Query<(&MenuItem, &mut Text)>,
+) {
+    if let Some(GameSaved(result)) = events.iter().last() {
+        let label = match result {
+            Ok(()) => "Game saved".to_string(),
+            Err(err) => format!("Save failed: {}", err),
+        };
+        set_item_label(&mut item_q, SAVE_ITEM, &label);
+    }
+}
+
+fn set_item_label(item_q: &mut Query<(&MenuItem, &mut Text)>, index: usize, label: &str) {
+    for (item, mut text) in item_q.iter_mut() {
+        if item.index == index {
+            text.sections[0].value = label.to_string();
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use bevy::{
+    prelude::*,
+    input::{Axis, gamepad::{Gamepad, GamepadAxis, GamepadAxisType, GamepadButton}},
+};
+
+use crate::{
+    GameState, FontInfos,
+    controls::{Action, ActionState, AxisDirection, ConnectedGamepads, ControlBindings, InputBinding, CONTROL_SCHEMES},
+};
+use super::{MenuCursor, MenuItem, spawn_menu, despawn_with};
+
+/// Leaves the capture with the binding unchanged
+const CANCEL_CAPTURE_KEY: KeyCode = KeyCode::Escape;
+/// Stick deflection needed before an axis is captured as a binding
+const CAPTURE_AXIS_THRESHOLD: f32 = 0.6;
+const CAPTURE_AXES: [GamepadAxisType; 4] = [
+    GamepadAxisType::LeftStickX,
+    GamepadAxisType::LeftStickY,
+    GamepadAxisType::RightStickX,
+    GamepadAxisType::RightStickY,
+];
+
+#[derive(Component)]
+struct RebindMenu;
+
+/// Menu layout: scheme selector, one entry per action, reset, back
+const ACTION_ITEMS_START: usize = 1;
+const RESET_ITEM: usize = ACTION_ITEMS_START + Action::ALL.len();
+const BACK_ITEM: usize = RESET_ITEM + 1;
+
+#[derive(Default)]
+struct RebindState {
+    /// Control scheme being edited
+    scheme: usize,
+    /// Action waiting for its new input, if any
+    capturing: Option<Action>,
+    /// Last input refused for being bound to another action already
+    conflict: Option<(InputBinding, Action)>,
+}
+
+pub struct RebindPlugin;
+impl Plugin for RebindPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<RebindState>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Rebinding)
+                    .with_system(setup_rebind_menu)
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Rebinding)
+                    .with_system(rebind_menu_input)
+                    .with_system(update_rebind_labels)
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Rebinding)
+                    .with_system(despawn_with::<RebindMenu>)
+            )
+        ;
+    }
+}
+
+fn setup_rebind_menu(
+    mut commands: Commands,
+    mut cursor: ResMut<MenuCursor>,
+    mut rebind: ResMut<RebindState>,
+    font_infos: Res<FontInfos>,
+) {
+    // Labels are filled in by update_rebind_labels
+    let items = vec![String::new(); BACK_ITEM + 1];
+
+    spawn_menu(&mut commands, &font_infos.main, "CONTROLS", &items, RebindMenu);
+    cursor.reset(items.len());
+    rebind.scheme = 0;
+    rebind.capturing = None;
+    rebind.conflict = None;
+}
+
+fn update_rebind_labels(
+    rebind: Res<RebindState>,
+    bindings: Res<ControlBindings>,
+    mut q: Query<(&MenuItem, &mut Text)>,
+) {
+    if !rebind.is_changed() && !bindings.is_changed() {
+        return;
+    }
+
+    for (item, mut text) in q.iter_mut() {
+        let action = item
+            .index
+            .checked_sub(ACTION_ITEMS_START)
+            .and_then(|idx| Action::ALL.get(idx));
+
+        let label = match action {
+            _ if item.index == 0 => format!("< Player {} >", rebind.scheme + 1),
+            Some(action) if rebind.capturing == Some(*action) => match rebind.conflict {
+                Some((binding, other)) => format!("{}: {} is used by {}", action.label(), binding.label(), other.label()),
+                None => format!("{}: press a key or button, Esc to cancel", action.label()),
+            },
+            Some(action) => {
+                let inputs = bindings
+                    .bindings(rebind.scheme, *action)
+                    .iter()
+                    .map(|binding| binding.label())
+                    .collect::<Vec<_>>()
+                    .join(" / ");
+                format!("{}: {}", action.label(), inputs)
+            }
+            None if item.index == RESET_ITEM => "Reset defaults".to_string(),
+            None => "Back".to_string(),
+        };
+        text.sections[0].value = label;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn rebind_menu_input(
+    mut state: ResMut<State<GameState>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut rebind: ResMut<RebindState>,
+    mut bindings: ResMut<ControlBindings>,
+    mut actions: ResMut<ActionState>,
+    gamepads: Res<ConnectedGamepads>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+) {
+    if let Some(action) = rebind.capturing {
+        if keys.just_pressed(CANCEL_CAPTURE_KEY) {
+            rebind.capturing = None;
+            rebind.conflict = None;
+            // Esc must not also leave the menu
+            actions.consume(Action::Pause);
+            return;
+        }
+
+        let captured = keys
+            .get_just_pressed()
+            .next()
+            .map(|key| InputBinding::Key(*key))
+            .or_else(|| buttons
+                .get_just_pressed()
+                .find(|button| gamepads.0.get(rebind.scheme) == Some(&button.0))
+                .map(|button| InputBinding::Button(button.1))
+            )
+            .or_else(|| captured_axis(gamepads.0.get(rebind.scheme), &axes));
+
+        if let Some(binding) = captured {
+            // The captured input must not also drive the menu this frame
+            for action in Action::ALL {
+                actions.consume(action);
+            }
+
+            // Keeps waiting for an input no other action uses
+            let scheme = rebind.scheme;
+            if let Some(other) = bindings.conflict(scheme, action, binding) {
+                rebind.conflict = Some((binding, other));
+                return;
+            }
+            bindings.rebind(scheme, action, binding);
+            bindings.save();
+            rebind.capturing = None;
+            rebind.conflict = None;
+        }
+        return;
+    }
+
+    if actions.just_pressed(Action::Pause) {
+        actions.consume(Action::Pause);
+        state.set(GameState::Settings).unwrap();
+        return;
+    }
+
+    cursor.navigate(&actions);
+    if actions.just_pressed(Action::Confirm) {
+        actions.consume(Action::Confirm);
+        match cursor.selected {
+            0 => rebind.scheme = (rebind.scheme + 1) % CONTROL_SCHEMES,
+            RESET_ITEM => {
+                bindings.reset_scheme(rebind.scheme);
+                bindings.save();
+            }
+            BACK_ITEM => state.set(GameState::Settings).unwrap(),
+            idx => rebind.capturing = Some(Action::ALL[idx - ACTION_ITEMS_START]),
+        }
+    }
+}
+
+fn captured_axis(
+    gamepad: Option<&Gamepad>,
+    axes: &Axis<GamepadAxis>,
+) -> Option<InputBinding> {
+    if let Some(gamepad) = gamepad {
+        for axis in CAPTURE_AXES {
+            let value = axes.get(GamepadAxis(*gamepad, axis)).unwrap_or(0.);
+            if value.abs() >= CAPTURE_AXIS_THRESHOLD {
+                let direction = if value < 0. {
+                    AxisDirection::Negative
+                } else {
+                    AxisDirection::Positive
+                };
+                return Some(InputBinding::Axis(axis, direction));
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+
+use crate::{GameState, FontInfos, controls::{Action, ActionState}, settings::Settings};
+use super::{MenuCursor, MenuItem, spawn_menu, despawn_with};
+
+/// Menu layout, every entry but the last two edits a setting
+const WINDOW_SIZE_ITEM: usize = 0;
+const FULLSCREEN_ITEM: usize = 1;
+const VSYNC_ITEM: usize = 2;
+const SOUND_ITEM: usize = 3;
+const EXPLOSIONS_ITEM: usize = 4;
+const CONTROLS_ITEM: usize = 5;
+const BACK_ITEM: usize = 6;
+
+#[derive(Component)]
+struct SettingsMenu;
+
+/// Menu the settings were opened from, to go back to
+pub struct SettingsOrigin(pub GameState);
+impl Default for SettingsOrigin {
+    fn default() -> Self {
+        Self(GameState::MainMenu)
+    }
+}
+
+pub struct SettingsMenuPlugin;
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SettingsOrigin>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Settings)
+                    .with_system(setup_settings_menu)
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Settings)
+                    .with_system(settings_menu_input)
+                    .with_system(update_settings_labels)
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Settings)
+                    .with_system(despawn_with::<SettingsMenu>)
+            )
+        ;
+    }
+}
+
+fn setup_settings_menu(
+    mut commands: Commands,
+    mut cursor: ResMut<MenuCursor>,
+    font_infos: Res<FontInfos>,
+    settings: Res<Settings>,
+) {
+    let items = (0..=BACK_ITEM)
+        .map(|index| settings_label(index, &settings))
+        .collect::<Vec<_>>();
+
+    spawn_menu(&mut commands, &font_infos.main, "SETTINGS", &items, SettingsMenu);
+    cursor.reset(items.len());
+}
+
+fn update_settings_labels(
+    settings: Res<Settings>,
+    mut q: Query<(&MenuItem, &mut Text)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (item, mut text) in q.iter_mut() {
+        text.sections[0].value = settings_label(item.index, &settings);
+    }
+}
+
+fn settings_label(index: usize, settings: &Settings) -> String {
+    let on_off = |on: bool| if on { "ON" } else { "OFF" };
+    match index {
+        WINDOW_SIZE_ITEM => format!(
+            "Window: {}x{} (on restart)",
+            settings.window_width,
+            settings.window_height,
+        ),
+        FULLSCREEN_ITEM => format!("Fullscreen: {}", on_off(settings.fullscreen)),
+        VSYNC_ITEM => format!("VSync: {}", on_off(settings.vsync)),
+        SOUND_ITEM => format!("Sound: {}", on_off(settings.sound_on())),
+        EXPLOSIONS_ITEM => format!("Explosions: {}", on_off(settings.explosions)),
+        CONTROLS_ITEM => "Controls".to_string(),
+        _ => "Back".to_string(),
+    }
+}
+
+/// Every change goes straight into `Settings`, which saves itself
+fn settings_menu_input(
+    mut state: ResMut<State<GameState>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut actions: ResMut<ActionState>,
+    mut settings: ResMut<Settings>,
+    origin: Res<SettingsOrigin>,
+) {
+    if actions.just_pressed(Action::Pause) {
+        actions.consume(Action::Pause);
+        state.set(origin.0.clone()).unwrap();
+        return;
+    }
+
+    cursor.navigate(&actions);
+    if actions.just_pressed(Action::Confirm) {
+        actions.consume(Action::Confirm);
+        match cursor.selected {
+            WINDOW_SIZE_ITEM => settings.next_window_size(),
+            FULLSCREEN_ITEM => settings.fullscreen = !settings.fullscreen,
+            VSYNC_ITEM => settings.vsync = !settings.vsync,
+            SOUND_ITEM => settings.toggle_sound(),
+            EXPLOSIONS_ITEM => settings.explosions = !settings.explosions,
+            CONTROLS_ITEM => state.set(GameState::Rebinding).unwrap(),
+            _ => state.set(origin.0.clone()).unwrap(),
+        }
+    }
+}
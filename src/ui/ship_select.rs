@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+
+use crate::{DataInfos, FontInfos, GameState, SpriteInfos, controls::{Action, ActionState}, entities::{Players, Ship, ShipDef, ShipDefs, ShipSprites}};
+use super::{MenuCursor, spawn_menu, despawn_with};
+
+#[derive(Component)]
+struct ShipSelectMenu;
+
+/// Player currently picking a ship, each player picks in turn
+#[derive(Default)]
+struct ShipSelectState {
+    player: usize,
+}
+
+pub struct ShipSelectPlugin;
+impl Plugin for ShipSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ShipSelectState>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::ShipSelect)
+                    .with_system(setup_ship_select)
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::ShipSelect)
+                    .with_system(ship_select_input)
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::ShipSelect)
+                    .with_system(despawn_with::<ShipSelectMenu>)
+            )
+        ;
+    }
+}
+
+/// Ships on offer, the default ship if the data has none
+fn ship_defs(data_infos: &DataInfos, ship_defs: &Assets<ShipDefs>) -> Vec<ShipDef> {
+    ship_defs
+        .get(&data_infos.ships)
+        .map(|defs| defs.ships.clone())
+        .filter(|ships| !ships.is_empty())
+        .unwrap_or_else(|| vec![ShipDef::default()])
+}
+
+fn ship_label(def: &ShipDef) -> String {
+    format!(
+        "{}  SPD {}  HP {}  {:?}",
+        def.name,
+        def.movement.max_speed,
+        def.hp,
+        def.weapon,
+    )
+}
+
+fn spawn_ship_menu(
+    commands: &mut Commands,
+    cursor: &mut MenuCursor,
+    font: &Handle<Font>,
+    player: usize,
+    ships: &[ShipDef],
+) {
+    let items = ships.iter().map(ship_label).collect::<Vec<_>>();
+    let title = format!("PLAYER {} SHIP", player + 1);
+
+    spawn_menu(commands, font, &title, &items, ShipSelectMenu);
+    cursor.reset(items.len());
+}
+
+fn setup_ship_select(
+    mut commands: Commands,
+    mut cursor: ResMut<MenuCursor>,
+    mut select: ResMut<ShipSelectState>,
+    font_infos: Res<FontInfos>,
+    data_infos: Res<DataInfos>,
+    defs: Res<Assets<ShipDefs>>,
+) {
+    select.player = 0;
+    let ships = ship_defs(&data_infos, &defs);
+    spawn_ship_menu(&mut commands, &mut cursor, &font_infos.main, select.player, &ships);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn ship_select_input(
+    mut commands: Commands,
+    mut state: ResMut<State<GameState>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut actions: ResMut<ActionState>,
+    mut select: ResMut<ShipSelectState>,
+    mut players: ResMut<Players>,
+    menu_q: Query<Entity, With<ShipSelectMenu>>,
+    font_infos: Res<FontInfos>,
+    data_infos: Res<DataInfos>,
+    sprite_infos: Res<SpriteInfos>,
+    defs: Res<Assets<ShipDefs>>,
+    ship_sprites: Res<ShipSprites>,
+) {
+    if actions.just_pressed(Action::Pause) {
+        actions.consume(Action::Pause);
+        state.set(GameState::MainMenu).unwrap();
+        return;
+    }
+
+    cursor.navigate(&actions);
+    if !actions.just_pressed(Action::Confirm) {
+        return;
+    }
+    actions.consume(Action::Confirm);
+
+    let ships = ship_defs(&data_infos, &defs);
+    let def = ships[cursor.selected.min(ships.len() - 1)].clone();
+    if let Some(slot) = players.slots.get_mut(select.player) {
+        slot.ship = Some(Ship::new(def, &ship_sprites, &sprite_infos.player));
+    }
+
+    select.player += 1;
+    if select.player < players.slots.len() {
+        // Same menu again for the next player
+        for entity in menu_q.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        spawn_ship_menu(&mut commands, &mut cursor, &font_infos.main, select.player, &ships);
+    } else {
+        state.set(GameState::InGame).unwrap();
+    }
+}